@@ -0,0 +1,58 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use nom::{
+    multi::count,
+    number::complete::{be_f32, be_i32},
+};
+use readap::data::{ByteOrder, DataArray, DataType};
+
+const LENGTHS: [usize; 3] = [1_024, 65_536, 1_048_576];
+
+/// Pseudo random big endian bytes, enough for `length` four byte values
+fn input(length: usize) -> Vec<u8> {
+    let mut state = 0x2545f491u32;
+    (0..length * 4)
+        .map(|_| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 24) as u8
+        })
+        .collect()
+}
+
+/// Compares the value by value nom decoder, which is used without the `bytemuck` feature,
+/// against the bulk decode `DataArray::parse_values` uses with it
+fn decode(c: &mut Criterion) {
+    for data_type in [DataType::Int32, DataType::Float32] {
+        let mut group = c.benchmark_group(format!("decode_{}", data_type.as_str()));
+        for length in LENGTHS {
+            let bytes = input(length);
+            group.throughput(Throughput::Bytes(bytes.len() as u64));
+
+            group.bench_with_input(BenchmarkId::new("scalar", length), &bytes, |b, bytes| {
+                b.iter(|| match data_type {
+                    DataType::Int32 => {
+                        count(be_i32::<_, nom::error::Error<_>>, length)(black_box(&bytes[..]))
+                            .map(|(_, v)| v.len())
+                    }
+                    _ => count(be_f32::<_, nom::error::Error<_>>, length)(black_box(&bytes[..]))
+                        .map(|(_, v)| v.len()),
+                })
+            });
+
+            group.bench_with_input(BenchmarkId::new("bytemuck", length), &bytes, |b, bytes| {
+                b.iter(|| {
+                    DataArray::parse_values(
+                        black_box(bytes),
+                        data_type.clone(),
+                        length,
+                        ByteOrder::BigEndian,
+                    )
+                    .map(|(_, v)| v.len())
+                })
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
@@ -0,0 +1,559 @@
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take, take_until, take_while1},
+    character::complete::multispace0,
+    combinator::opt,
+    multi::many0,
+    number::complete::be_u32,
+    sequence::{delimited, preceded, terminated},
+    IResult,
+};
+
+use crate::{
+    data::{ByteOrder, DataArray, DataType},
+    dds::{DdsArray, DdsDataset, DdsValue},
+    errors::Error,
+};
+
+pub const CHUNK_DATA: u8 = 0x00;
+pub const CHUNK_END: u8 = 0x01;
+pub const CHUNK_ERROR: u8 = 0x02;
+pub const CHUNK_LITTLE_ENDIAN: u8 = 0x04;
+
+/// The DMR attribute servers attach to each variable when its data is followed by a CRC32
+pub const CHECKSUM_ATTRIBUTE: &str = "_DAP4_Checksum_CRC32";
+
+#[derive(Clone, Debug)]
+pub struct Dap4Chunk<'a> {
+    pub flags: u8,
+    pub data: &'a [u8],
+}
+
+impl<'a> Dap4Chunk<'a> {
+    pub fn parse(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+        let (input, header) = be_u32(input)?;
+        let flags = (header >> 24) as u8;
+        let length = header & 0x00ff_ffff;
+        let (input, data) = take(length)(input)?;
+
+        Ok((input, Dap4Chunk { flags, data }))
+    }
+
+    pub fn is_end(&self) -> bool {
+        self.flags & CHUNK_END != 0
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.flags & CHUNK_ERROR != 0
+    }
+
+    pub fn is_little_endian(&self) -> bool {
+        self.flags & CHUNK_LITTLE_ENDIAN != 0
+    }
+}
+
+#[derive(Clone, Debug)]
+struct XmlTag<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, &'a str)>,
+    closing: bool,
+    self_closing: bool,
+}
+
+impl<'a> XmlTag<'a> {
+    fn attribute(&self, key: &str) -> Option<&'a str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| *v)
+    }
+}
+
+fn xml_name(input: &str) -> IResult<&str, &str> {
+    take_while1(|c: char| c.is_alphanumeric() || c == '_' || c == ':' || c == '-' || c == '.')(
+        input,
+    )
+}
+
+fn xml_attribute(input: &str) -> IResult<&str, (&str, &str)> {
+    let (input, key) = preceded(multispace0, xml_name)(input)?;
+    let (input, _) = delimited(multispace0, tag("="), multispace0)(input)?;
+    let (input, value) = delimited(tag("\""), take_until("\""), tag("\""))(input)?;
+    Ok((input, (key, value)))
+}
+
+fn xml_skipped(input: &str) -> IResult<&str, &str> {
+    alt((
+        delimited(tag("<?"), take_until("?>"), tag("?>")),
+        delimited(tag("<!--"), take_until("-->"), tag("-->")),
+    ))(input)
+}
+
+fn xml_tag(input: &str) -> IResult<&str, XmlTag<'_>> {
+    let (input, _) = take_until("<")(input)?;
+    let (input, _) = many0(terminated(xml_skipped, take_until("<")))(input)?;
+
+    let (input, _) = tag("<")(input)?;
+    let (input, closing) = opt(tag("/"))(input)?;
+    let (input, name) = xml_name(input)?;
+    let (input, attributes) = many0(xml_attribute)(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, self_closing) = opt(tag("/"))(input)?;
+    let (input, _) = tag(">")(input)?;
+
+    Ok((
+        input,
+        XmlTag {
+            name,
+            attributes,
+            closing: closing.is_some(),
+            self_closing: self_closing.is_some(),
+        },
+    ))
+}
+
+/// Parses the atomic array variables declared in a DAP4 DMR into a `DdsDataset`,
+/// so DAP4 responses can share the same layout helpers as DAP2 responses.
+pub fn parse_dmr(input: &str) -> Result<DdsDataset, Error> {
+    let (_, tags) = many0(xml_tag)(input).map_err(|_| Error::ParseError)?;
+
+    let mut name = String::new();
+    let mut dimensions: Vec<(String, u32)> = Vec::new();
+    let mut values = Vec::new();
+    let mut current: Option<DdsArray> = None;
+    let mut depth = 0usize;
+
+    for t in tags {
+        if t.closing {
+            depth = depth.saturating_sub(1);
+            if depth == 1 {
+                if let Some(array) = current.take() {
+                    values.push(DdsValue::Array(array));
+                }
+            }
+            continue;
+        }
+
+        match (depth, t.name) {
+            (0, "Dataset") => {
+                name = t.attribute("name").unwrap_or_default().to_string();
+            }
+            (1, "Dimension") => {
+                let dim_name = t.attribute("name").ok_or(Error::ParseError)?;
+                let size = t
+                    .attribute("size")
+                    .and_then(|s| s.parse::<u32>().ok())
+                    .ok_or(Error::ParseError)?;
+                dimensions.push((dim_name.to_string(), size));
+            }
+            (1, "Attribute") => {}
+            (1, type_name) => {
+                let data_type = match DataType::parse(type_name) {
+                    Ok(("", d)) if d != DataType::String => d,
+                    _ => return Err(Error::NotImplemented),
+                };
+
+                let array = DdsArray {
+                    data_type,
+                    name: t.attribute("name").ok_or(Error::ParseError)?.to_string(),
+                    coords: vec![],
+                };
+
+                if t.self_closing {
+                    values.push(DdsValue::Array(array));
+                } else {
+                    current = Some(array);
+                }
+            }
+            (2, "Dim") => {
+                let array = current.as_mut().ok_or(Error::ParseError)?;
+                let coord = match (t.attribute("name"), t.attribute("size")) {
+                    (Some(dim_name), _) => {
+                        let dim_name = dim_name.trim_start_matches('/');
+                        dimensions
+                            .iter()
+                            .find(|d| d.0 == dim_name)
+                            .cloned()
+                            .ok_or(Error::ParseError)?
+                    }
+                    (None, Some(size)) => (
+                        String::new(),
+                        size.parse::<u32>().map_err(|_| Error::ParseError)?,
+                    ),
+                    (None, None) => return Err(Error::ParseError),
+                };
+                array.coords.push(coord);
+            }
+            _ => {}
+        }
+
+        if !t.self_closing {
+            depth += 1;
+        }
+    }
+
+    Ok(DdsDataset { name, values })
+}
+
+/// Whether any variable in the DMR carries the checksum attribute, so a description that
+/// merely mentions its name does not turn on verification
+fn declares_checksums(dmr: &str) -> Result<bool, Error> {
+    let (_, tags) = many0(xml_tag)(dmr).map_err(|_| Error::ParseError)?;
+    Ok(tags
+        .iter()
+        .any(|t| t.name == "Attribute" && t.attribute("name") == Some(CHECKSUM_ATTRIBUTE)))
+}
+
+#[derive(Clone, Debug)]
+pub struct Dap4Response {
+    pub dmr: String,
+    pub dds: DdsDataset,
+    pub data_bytes: Vec<u8>,
+    pub byte_order: ByteOrder,
+    /// Whether each variable's data is followed by its CRC32
+    pub checksums: bool,
+}
+
+impl Dap4Response {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut chunks = Vec::new();
+        let mut input = bytes;
+        while !input.is_empty() {
+            let (remaining, chunk) = Dap4Chunk::parse(input).map_err(|_| Error::ParseError)?;
+            if chunk.is_error() {
+                return Err(Error::InvalidData);
+            }
+
+            let is_end = chunk.is_end();
+            chunks.push(chunk);
+            input = remaining;
+
+            if is_end {
+                break;
+            }
+        }
+
+        let (dmr_chunk, data_chunks) = chunks.split_first().ok_or(Error::InvalidData)?;
+        let dmr = String::from_utf8_lossy(dmr_chunk.data)
+            .trim_end_matches("\r\n")
+            .to_string();
+        let dds = parse_dmr(&dmr)?;
+
+        let byte_order = match data_chunks.first() {
+            Some(c) if c.is_little_endian() => ByteOrder::LittleEndian,
+            _ => ByteOrder::BigEndian,
+        };
+
+        if data_chunks
+            .iter()
+            .any(|c| c.is_little_endian() != (byte_order == ByteOrder::LittleEndian))
+        {
+            return Err(Error::InvalidData);
+        }
+
+        let data_bytes = data_chunks
+            .iter()
+            .flat_map(|c| c.data.iter().copied())
+            .collect();
+
+        let checksums = declares_checksums(&dmr)?;
+        let response = Dap4Response {
+            dmr,
+            dds,
+            data_bytes,
+            byte_order,
+            checksums,
+        };
+
+        if response.checksums {
+            response.verify_checksums()?;
+        }
+
+        Ok(response)
+    }
+
+    /// Checks the CRC32 trailing each variable against its data
+    fn verify_checksums(&self) -> Result<(), Error> {
+        let mut offset = 0;
+        for v in &self.dds.values {
+            let length = dap4_byte_count(v.array()?);
+            let data = self
+                .data_bytes
+                .get(offset..offset + length)
+                .ok_or(Error::InvalidData)?;
+            let stored: [u8; 4] = self
+                .data_bytes
+                .get(offset + length..offset + length + 4)
+                .and_then(|b| b.try_into().ok())
+                .ok_or(Error::InvalidData)?;
+            let stored = match self.byte_order {
+                ByteOrder::BigEndian => u32::from_be_bytes(stored),
+                ByteOrder::LittleEndian => u32::from_le_bytes(stored),
+            };
+
+            if crc32fast::hash(data) != stored {
+                return Err(Error::ChecksumMismatch { variable: v.name() });
+            }
+
+            offset += length + 4;
+        }
+
+        Ok(())
+    }
+
+    pub fn variables(&self) -> Vec<String> {
+        self.dds.values.iter().map(|v| v.name()).collect()
+    }
+
+    pub fn variable_index(&self, key: &str) -> Option<usize> {
+        self.dds.values.iter().position(|v| v.name() == key)
+    }
+
+    pub fn variable_byte_offset(&self, key: &str) -> Option<usize> {
+        let position = self.variable_index(key)?;
+        let offset = self.dds.values[..position].iter().try_fold(0, |acc, v| {
+            Some(acc + dap4_byte_count(v.array().ok()?) + self.checksum_len())
+        })?;
+        Some(offset)
+    }
+
+    fn checksum_len(&self) -> usize {
+        if self.checksums {
+            4
+        } else {
+            0
+        }
+    }
+
+    pub fn variable_data(&self, key: &str) -> Result<DataArray, Error> {
+        let index = self.variable_index(key).ok_or(Error::ParseError)?;
+        let offset = self.variable_byte_offset(key).ok_or(Error::ParseError)?;
+        let array = self.dds.values[index].array()?;
+
+        let bytes = self
+            .data_bytes
+            .get(offset..offset + dap4_byte_count(array))
+            .ok_or(Error::InvalidData)?;
+        let (_, data) = DataArray::parse_values(
+            bytes,
+            array.data_type.clone(),
+            array.array_length() as usize,
+            self.byte_order,
+        )
+        .map_err(|_| Error::ParseError)?;
+
+        Ok(data)
+    }
+}
+
+/// DAP4 fixed size arrays are serialized without the DAP2 length prefix
+fn dap4_byte_count(array: &DdsArray) -> usize {
+    array.array_length() as usize * array.data_type.byte_count()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        data::{ByteOrder, DataArray, DataType},
+        errors::Error,
+    };
+
+    use super::{parse_dmr, xml_tag, Dap4Chunk, Dap4Response};
+
+    #[test]
+    fn parse_chunk() {
+        let input = [0x05, 0x00, 0x00, 0x02, 0xab, 0xcd, 0xff];
+        let (rest, chunk) = Dap4Chunk::parse(&input).unwrap();
+        assert_eq!(chunk.data, &[0xab, 0xcd]);
+        assert!(chunk.is_end());
+        assert!(chunk.is_little_endian());
+        assert!(!chunk.is_error());
+        assert_eq!(rest, &[0xff]);
+    }
+
+    #[test]
+    fn parse_tag() {
+        let (_, t) = xml_tag(r#"<?xml version="1.0"?>  <Dim name="/time"/>"#).unwrap();
+        assert_eq!(t.name, "Dim");
+        assert_eq!(t.attribute("name"), Some("/time"));
+        assert!(t.self_closing);
+        assert!(!t.closing);
+
+        let (_, t) = xml_tag("</Float32>").unwrap();
+        assert_eq!(t.name, "Float32");
+        assert!(t.closing);
+    }
+
+    #[test]
+    fn parse_dmr_arrays() {
+        let input = r#"<?xml version="1.0" encoding="ISO-8859-1"?>
+<Dataset name="sample.nc" dapVersion="4.0" dmrVersion="1.0">
+    <Dimension name="time" size="3"/>
+    <Dimension name="station" size="2"/>
+    <Int32 name="time">
+        <Dim name="/time"/>
+        <Attribute name="units" type="String">
+            <Value>seconds since 1970-01-01</Value>
+        </Attribute>
+    </Int32>
+    <Float32 name="temperature">
+        <Dim name="/time"/>
+        <Dim name="/station"/>
+    </Float32>
+</Dataset>"#;
+
+        let dataset = parse_dmr(input).unwrap();
+        assert_eq!(dataset.name, "sample.nc");
+        assert_eq!(dataset.values.len(), 2);
+
+        let time = dataset.values[0].array().unwrap();
+        assert_eq!(time.name, "time");
+        assert_eq!(time.data_type, DataType::Int32);
+        assert_eq!(time.coords, vec![("time".to_string(), 3)]);
+
+        let temperature = dataset.values[1].array().unwrap();
+        assert_eq!(temperature.name, "temperature");
+        assert_eq!(temperature.data_type, DataType::Float32);
+        assert_eq!(temperature.array_length(), 6);
+    }
+
+    #[test]
+    fn parse_little_endian_response() {
+        let dmr = br#"<Dataset name="le.nc">
+    <Dimension name="x" size="2"/>
+    <Int32 name="x">
+        <Dim name="/x"/>
+    </Int32>
+    <Float32 name="value">
+        <Dim name="/x"/>
+    </Float32>
+</Dataset>
+"#;
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&7i32.to_le_bytes());
+        data.extend_from_slice(&(-3i32).to_le_bytes());
+        data.extend_from_slice(&1.5f32.to_le_bytes());
+        data.extend_from_slice(&(-2.25f32).to_le_bytes());
+
+        let mut input = Vec::new();
+        input.extend_from_slice(&(0x0400_0000u32 | dmr.len() as u32).to_be_bytes());
+        input.extend_from_slice(dmr);
+        input.extend_from_slice(&(0x0500_0000u32 | data.len() as u32).to_be_bytes());
+        input.extend_from_slice(&data);
+
+        let response = Dap4Response::from_bytes(&input).unwrap();
+        assert_eq!(response.byte_order, ByteOrder::LittleEndian);
+
+        let x = response.variable_data("x").unwrap();
+        assert!(matches!(x, DataArray::Int32(v) if v == vec![7, -3]));
+
+        let value = response.variable_data("value").unwrap();
+        assert!(matches!(value, DataArray::Float32(v) if v == vec![1.5, -2.25]));
+    }
+
+    #[test]
+    fn parse_truncated_response() {
+        let dmr = br#"<Dataset name="short.nc">
+    <Dimension name="x" size="2"/>
+    <Int32 name="x">
+        <Dim name="/x"/>
+    </Int32>
+    <Int32 name="y">
+        <Dim name="/x"/>
+    </Int32>
+</Dataset>
+"#;
+
+        let mut input = Vec::new();
+        input.extend_from_slice(&(0x0400_0000u32 | dmr.len() as u32).to_be_bytes());
+        input.extend_from_slice(dmr);
+        input.extend_from_slice(&0x0400_0004u32.to_be_bytes());
+        input.extend_from_slice(&7i32.to_be_bytes());
+
+        let response = Dap4Response::from_bytes(&input).unwrap();
+        assert!(matches!(
+            response.variable_data("x"),
+            Err(Error::InvalidData)
+        ));
+        assert!(matches!(
+            response.variable_data("y"),
+            Err(Error::InvalidData)
+        ));
+    }
+
+    #[test]
+    fn parse_response_with_checksums() {
+        let dmr = br#"<Dataset name="crc.nc">
+    <Dimension name="x" size="2"/>
+    <Int32 name="x">
+        <Dim name="/x"/>
+        <Attribute name="_DAP4_Checksum_CRC32" type="UInt32"/>
+    </Int32>
+    <Float32 name="value">
+        <Dim name="/x"/>
+        <Attribute name="_DAP4_Checksum_CRC32" type="UInt32"/>
+    </Float32>
+</Dataset>
+"#;
+
+        let x = [7i32, -3]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect::<Vec<_>>();
+        let value = [1.5f32, -2.25]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect::<Vec<_>>();
+
+        let response = |value_checksum: u32| {
+            let mut data = x.clone();
+            data.extend_from_slice(&crc32fast::hash(&x).to_be_bytes());
+            data.extend_from_slice(&value);
+            data.extend_from_slice(&value_checksum.to_be_bytes());
+
+            let mut input = Vec::new();
+            input.extend_from_slice(&(dmr.len() as u32).to_be_bytes());
+            input.extend_from_slice(dmr);
+            input.extend_from_slice(&(0x0100_0000u32 | data.len() as u32).to_be_bytes());
+            input.extend_from_slice(&data);
+            Dap4Response::from_bytes(&input)
+        };
+
+        let valid = response(crc32fast::hash(&value)).unwrap();
+        assert!(valid.checksums);
+        assert_eq!(valid.variable_byte_offset("value"), Some(12));
+        let data = valid.variable_data("value").unwrap();
+        assert!(matches!(data, DataArray::Float32(v) if v == vec![1.5, -2.25]));
+
+        assert!(matches!(
+            response(crc32fast::hash(&value) ^ 1),
+            Err(Error::ChecksumMismatch { variable }) if variable == "value"
+        ));
+    }
+
+    #[test]
+    fn checksum_attribute_mentioned_in_text() {
+        let dmr = br#"<Dataset name="crc.nc">
+    <Dimension name="x" size="1"/>
+    <Int32 name="x">
+        <Dim name="/x"/>
+    </Int32>
+    <Attribute name="comment" type="String">
+        <Value>Served without _DAP4_Checksum_CRC32</Value>
+    </Attribute>
+</Dataset>
+"#;
+
+        let mut input = Vec::new();
+        input.extend_from_slice(&(dmr.len() as u32).to_be_bytes());
+        input.extend_from_slice(dmr);
+        input.extend_from_slice(&0x0100_0004u32.to_be_bytes());
+        input.extend_from_slice(&7i32.to_be_bytes());
+
+        let response = Dap4Response::from_bytes(&input).unwrap();
+        assert!(!response.checksums);
+        let x = response.variable_data("x").unwrap();
+        assert!(matches!(x, DataArray::Int32(v) if v == vec![7]));
+    }
+}
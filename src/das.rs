@@ -1,10 +1,12 @@
 use std::collections::HashMap;
 
 use nom::{
-    bytes::complete::{tag, take_till, take_until},
-    character::complete::{multispace0, newline},
-    multi::many_till,
-    sequence::{preceded, terminated},
+    branch::alt,
+    bytes::complete::{tag, take_till},
+    character::complete::{multispace0, multispace1, newline, not_line_ending},
+    combinator::recognize,
+    multi::{many0, many_till},
+    sequence::{pair, preceded, terminated},
     IResult,
 };
 
@@ -18,6 +20,10 @@ pub struct DasAttribute {
     pub data_type: DataType,
     pub name: String,
     pub value: DataValue,
+    /// Every value of a comma separated attribute, the first of which is `value`
+    pub values: Vec<DataValue>,
+    /// The attribute value exactly as written in the DAS, before any parsing
+    pub raw: String,
 }
 
 impl DasAttribute {
@@ -30,26 +36,140 @@ impl DasAttribute {
         let name = name.to_string();
 
         let (input, _) = multispace0(input)?;
-        let (input, raw_value) = take_until(";")(input)?;
+        let (input, raw_value) = attribute_value(input)?;
         let (input, _) = tag(";")(input)?;
 
-        let value = match data_type {
-            DataType::Int32 => DataValue::Int32(raw_value.parse::<i32>().unwrap()),
-            DataType::Float32 => DataValue::Float32(raw_value.parse::<f32>().unwrap()),
-            DataType::String => DataValue::String(raw_value.replace("\"", "")),
+        // Noncompliant servers sometimes leave strings unquoted, in which case the whole
+        // value is one string and commas or backslashes in it are literal
+        let raw_values = match data_type {
+            DataType::String if !raw_value.trim_start().starts_with('"') => vec![raw_value],
+            _ => split_values(raw_value),
         };
 
+        let values = raw_values
+            .into_iter()
+            .map(|v| parse_value(&data_type, v))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| {
+                nom::Err::Error(nom::error::Error::new(
+                    raw_value,
+                    nom::error::ErrorKind::Verify,
+                ))
+            })?;
+        let value = values[0].clone();
+
         Ok((
             input,
             DasAttribute {
                 data_type,
                 name,
                 value,
+                values,
+                raw: raw_value.trim().to_string(),
             },
         ))
     }
 }
 
+/// The position of the first `delimiter` that is not inside a quoted string
+fn find_unquoted(input: &str, delimiter: char) -> Option<usize> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in input.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Takes the raw attribute value up to the terminating `;`. Quotes and escapes are only
+/// tracked for quoted values, so a stray `"` or `\` in an unquoted one is literal.
+fn attribute_value(input: &str) -> IResult<&str, &str> {
+    let end = if input.starts_with('"') {
+        find_unquoted(input, ';')
+    } else {
+        input.find(';')
+    };
+    match end {
+        Some(i) => Ok((&input[i..], &input[..i])),
+        None => Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::TakeUntil,
+        ))),
+    }
+}
+
+/// Splits a raw attribute value on the commas separating the entries of a list
+fn split_values(raw_value: &str) -> Vec<&str> {
+    let mut values = Vec::new();
+    let mut remaining = raw_value;
+    while let Some(i) = find_unquoted(remaining, ',') {
+        values.push(&remaining[..i]);
+        remaining = &remaining[i + 1..];
+    }
+    values.push(remaining);
+    values
+}
+
+fn parse_value(data_type: &DataType, raw_value: &str) -> Option<DataValue> {
+    match data_type {
+        // DAP2 bytes are unsigned, so values above 127 wrap around into the signed range
+        DataType::Byte => raw_value
+            .trim()
+            .parse::<i8>()
+            .ok()
+            .or_else(|| raw_value.trim().parse::<u8>().ok().map(|b| b as i8))
+            .map(DataValue::Byte),
+        DataType::Int32 => raw_value.trim().parse::<i32>().ok().map(DataValue::Int32),
+        DataType::Int64 => raw_value.trim().parse::<i64>().ok().map(DataValue::Int64),
+        DataType::UInt64 => raw_value.trim().parse::<u64>().ok().map(DataValue::UInt64),
+        DataType::Float32 => parse_float(raw_value).map(DataValue::Float32),
+        DataType::String if !raw_value.trim_start().starts_with('"') => {
+            Some(DataValue::String(raw_value.trim().to_string()))
+        }
+        DataType::String => Some(DataValue::String(unquote(raw_value))),
+    }
+}
+
+/// Parses a float attribute, accepting the `nan` and `inf` spellings servers write for fill
+/// values in any case and with an optional sign
+fn parse_float(raw_value: &str) -> Option<f32> {
+    let value = raw_value.trim();
+    match value.to_ascii_lowercase().as_str() {
+        "nan" | "+nan" | "-nan" => Some(f32::NAN),
+        "inf" | "+inf" => Some(f32::INFINITY),
+        "-inf" => Some(f32::NEG_INFINITY),
+        _ => value.parse::<f32>().ok(),
+    }
+}
+
+/// Strips the surrounding quotes from a DAS string value and unescapes `\"` and `\\` inside it
+fn unquote(raw_value: &str) -> String {
+    let value = raw_value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+
+    let mut unquoted = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&next)) if next == '"' || next == '\\' => {
+                unquoted.push(next);
+                chars.next();
+            }
+            (c, _) => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
 impl TryInto<String> for DasAttribute {
     type Error = Error;
 
@@ -82,8 +202,8 @@ pub fn parse_das_variable(input: &str) -> IResult<&str, (String, DasVariable)> {
     let (input, _) = newline(input)?;
 
     let (input, (attributes, _)) = many_till(
-        preceded(multispace0, terminated(DasAttribute::parse, newline)),
-        preceded(multispace0, tag("}")),
+        preceded(ignored, terminated(DasAttribute::parse, newline)),
+        preceded(ignored, tag("}")),
     )(input)?;
 
     let mut attrs = HashMap::new();
@@ -96,11 +216,24 @@ pub fn parse_das_variable(input: &str) -> IResult<&str, (String, DasVariable)> {
 
 pub type DasAttributes = HashMap<String, DasVariable>;
 
+/// Skips any whitespace, blank lines, and `//` comment lines between DAS items
+fn ignored(input: &str) -> IResult<&str, ()> {
+    let (input, _) = many0(alt((
+        multispace1,
+        recognize(pair(tag("//"), not_line_ending)),
+    )))(input)?;
+    Ok((input, ()))
+}
+
 fn parse_das_attributes_inner(input: &str) -> IResult<&str, DasAttributes> {
     let (input, _) = tag("Attributes {")(input)?;
     let (input, _) = newline(input)?;
 
-    let (input, (vars, _)) = many_till(terminated(parse_das_variable, newline), tag("}"))(input)?;
+    // Blocks are usually one per line, but some servers start the next one right after `}`
+    let (input, (vars, _)) = many_till(
+        preceded(ignored, parse_das_variable),
+        preceded(ignored, tag("}")),
+    )(input)?;
 
     let mut attributes = HashMap::new();
 
@@ -160,6 +293,113 @@ mod tests {
         assert!((value - 999.0).abs() < 0.0001);
     }
 
+    #[test]
+    fn parse_attribute_special_floats() {
+        let (_, attr) = DasAttribute::parse("Float32 _FillValue nan ;").unwrap();
+        assert!(matches!(attr.value, DataValue::Float32(f) if f.is_nan()));
+
+        let (_, attr) = DasAttribute::parse("Float32 scale inf;").unwrap();
+        assert!(matches!(attr.value, DataValue::Float32(f) if f.is_infinite() && f > 0.0));
+
+        let (_, attr) = DasAttribute::parse("Float32 valid_range -Inf, +INF, NaN;").unwrap();
+        assert!(matches!(attr.values[0], DataValue::Float32(f) if f == f32::NEG_INFINITY));
+        assert!(matches!(attr.values[1], DataValue::Float32(f) if f == f32::INFINITY));
+        assert!(matches!(attr.values[2], DataValue::Float32(f) if f.is_nan()));
+    }
+
+    #[test]
+    fn parse_attribute_list() {
+        let input = "Int32 _ChunkSizes 1, 721, 1440;";
+        let (_, attr) = DasAttribute::parse(input).unwrap();
+        assert!(matches!(attr.value, DataValue::Int32(1)));
+        assert_eq!(attr.values.len(), 3);
+        assert!(matches!(attr.values[2], DataValue::Int32(1440)));
+
+        let input = r#"String flag_meanings "good, really", "bad";"#;
+        let (_, attr) = DasAttribute::parse(input).unwrap();
+        assert_eq!(attr.values.len(), 2);
+        assert!(matches!(&attr.values[0], DataValue::String(s) if s == "good, really"));
+        assert!(matches!(&attr.values[1], DataValue::String(s) if s == "bad"));
+
+        assert!(DasAttribute::parse("Int32 _FillValue nope;").is_err());
+    }
+
+    #[test]
+    fn parse_attribute_raw_value() {
+        let input = "Float32 valid_max 3.4028234663852886e+38;";
+        let (_, attr) = DasAttribute::parse(input).unwrap();
+        assert_eq!(attr.raw, "3.4028234663852886e+38");
+        assert!(matches!(attr.value, DataValue::Float32(f) if f == f32::MAX));
+
+        let input = r#"String units "degrees_north" ;"#;
+        let (_, attr) = DasAttribute::parse(input).unwrap();
+        assert_eq!(attr.raw, r#""degrees_north""#);
+    }
+
+    #[test]
+    fn parse_string_attribute_quoting() {
+        let input = r#"String comment "";"#;
+        let (_, empty) = DasAttribute::parse(input).unwrap();
+        assert!(matches!(empty.value, DataValue::String(s) if s.is_empty()));
+
+        let input = r#"String comment "say \"hi\"";"#;
+        let (_, escaped) = DasAttribute::parse(input).unwrap();
+        assert!(matches!(escaped.value, DataValue::String(s) if s == r#"say "hi""#));
+    }
+
+    #[test]
+    fn parse_unquoted_string_attribute() {
+        let input = "String title   My  Dataset Title ;";
+        let (rest, title) = DasAttribute::parse(input).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(title.values.len(), 1);
+        assert!(matches!(title.value, DataValue::String(s) if s == "My  Dataset Title"));
+
+        let input = r"String source Temperature, Salinity, C:\data;";
+        let (_, source) = DasAttribute::parse(input).unwrap();
+        assert_eq!(source.values.len(), 1);
+        assert!(
+            matches!(source.value, DataValue::String(s) if s == r"Temperature, Salinity, C:\data")
+        );
+    }
+
+    #[test]
+    fn parse_string_attribute_escapes() {
+        let input = r#"String location "40.969 6\"N; 71.127 W";"#;
+        let (rest, quoted) = DasAttribute::parse(input).unwrap();
+        assert!(rest.is_empty());
+        assert!(matches!(quoted.value, DataValue::String(s) if s == r#"40.969 6"N; 71.127 W"#));
+
+        let input = r#"String path "C:\\data\\\"raw\"";"#;
+        let (_, path) = DasAttribute::parse(input).unwrap();
+        assert!(matches!(path.value, DataValue::String(s) if s == r#"C:\data\"raw""#));
+
+        let input = r#"String windows "C:\data\new\tab\t";"#;
+        let (_, windows) = DasAttribute::parse(input).unwrap();
+        assert!(matches!(windows.value, DataValue::String(s) if s == r"C:\data\new\tab\t"));
+
+        let input = r#"String trailing "ends with \\";"#;
+        let (_, trailing) = DasAttribute::parse(input).unwrap();
+        assert!(matches!(trailing.value, DataValue::String(s) if s == r#"ends with \"#));
+    }
+
+    #[test]
+    fn parse_unquoted_string_with_quote_or_backslash() {
+        let input = r#"Attributes {
+    depth {
+        String note 12" snow;
+        String path C:\;
+        String units "m";
+    }
+}"#;
+        let attrs = parse_das_attributes(input).unwrap();
+        let depth = &attrs["depth"];
+        assert_eq!(depth.len(), 3);
+        assert!(matches!(&depth["note"].value, DataValue::String(s) if s == r#"12" snow"#));
+        assert!(matches!(&depth["path"].value, DataValue::String(s) if s == r"C:\"));
+        assert!(matches!(&depth["units"].value, DataValue::String(s) if s == "m"));
+    }
+
     #[test]
     fn parse_variable() {
         let input = r#"    spectral_wave_density {
@@ -210,4 +450,47 @@ mod tests {
         assert!(attrs.contains_key("time"));
         assert!(attrs.contains_key("frequency"));
     }
+
+    #[test]
+    fn parse_das_with_blank_lines_and_comments() {
+        let input = r#"Attributes {
+
+    time {
+        // seconds since the epoch
+        String units "seconds since 1970-01-01 00:00:00 UTC";
+
+    }
+    // global attributes follow
+
+    NC_GLOBAL {
+        String title "Sample";
+    }
+
+}"#;
+        let attrs = parse_das_attributes(input).unwrap();
+
+        assert_eq!(attrs.len(), 2);
+        assert_eq!(attrs["time"].len(), 1);
+        assert!(
+            matches!(&attrs["NC_GLOBAL"]["title"].value, DataValue::String(s) if s == "Sample")
+        );
+    }
+
+    #[test]
+    fn parse_das_with_blocks_on_one_line() {
+        let input = r#"Attributes {
+    time {
+        String units "seconds since 1970-01-01 00:00:00 UTC";
+    } frequency {
+        String units "Hz";
+    }latitude {
+        String units "degrees_north";
+    }
+}"#;
+        let attrs = parse_das_attributes(input).unwrap();
+
+        assert_eq!(attrs.len(), 3);
+        assert!(matches!(&attrs["frequency"]["units"].value, DataValue::String(s) if s == "Hz"));
+        assert!(attrs.contains_key("latitude"));
+    }
 }
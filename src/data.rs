@@ -2,24 +2,40 @@ use nom::{
     branch::alt,
     bytes::complete::tag,
     multi::count,
-    number::complete::{be_f32, be_i32, be_u32},
+    number::complete::{
+        be_f32, be_i32, be_i64, be_i8, be_u32, be_u64, le_f32, le_i32, le_i64, le_u64,
+    },
     IResult,
 };
 
-use crate::errors::Error;
+use crate::{errors::Error, indexing::strides};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DataType {
+    Byte,
     Int32,
+    Int64,
+    UInt64,
     Float32,
     String,
 }
 
 impl DataType {
     pub fn parse(input: &str) -> IResult<&str, Self> {
-        let (input, dtype) = alt((tag("Int32"), tag("Float32"), tag("String")))(input)?;
+        let (input, dtype) = alt((
+            tag("Byte"),
+            tag("Int32"),
+            tag("Int64"),
+            tag("UInt64"),
+            tag("Float32"),
+            tag("String"),
+        ))(input)?;
         let dtype = match dtype {
+            "Byte" => Self::Byte,
             "Int32" => Self::Int32,
+            "Int64" => Self::Int64,
+            "UInt64" => Self::UInt64,
             "Float32" => Self::Float32,
             "String" => Self::String,
             _ => unreachable!(),
@@ -28,22 +44,101 @@ impl DataType {
         Ok((input, dtype))
     }
 
+    /// The name of the type as written in a DDS
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataType::Byte => "Byte",
+            DataType::Int32 => "Int32",
+            DataType::Int64 => "Int64",
+            DataType::UInt64 => "UInt64",
+            DataType::Float32 => "Float32",
+            DataType::String => "String",
+        }
+    }
+
     pub fn byte_count(&self) -> usize {
         match self {
+            DataType::Byte => 1,
             DataType::Int32 => 4,
+            DataType::Int64 => 8,
+            DataType::UInt64 => 8,
             DataType::Float32 => 4,
             DataType::String => unreachable!(),
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    BigEndian,
+    LittleEndian,
+}
+
+/// With the `serde` feature values serialize externally tagged, e.g. `{"Float32":1.0}`. Formats
+/// without NaN or infinity, like JSON, write those floats as null and cannot read them back.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DataValue {
+    /// DAP2 bytes are unsigned. The raw bits are kept as `i8`, and numeric conversions read
+    /// them as `u8`.
+    Byte(i8),
     Int32(i32),
+    Int64(i64),
+    UInt64(u64),
     Float32(f32),
     String(String),
 }
 
+impl DataValue {
+    pub fn try_into_bool(&self) -> Result<bool, Error> {
+        match self {
+            DataValue::Byte(b) => Ok(*b != 0),
+            DataValue::Int32(i) => Ok(*i != 0),
+            DataValue::Int64(i) => Ok(*i != 0),
+            DataValue::UInt64(i) => Ok(*i != 0),
+            DataValue::Float32(f) => Ok(*f != 0.0),
+            DataValue::String(_) => Err(Error::InvalidTypecast),
+        }
+    }
+
+    /// Whether the value is NaN or equal to any of the fill values. The comparison is made at
+    /// the value's own precision, so a Float32 fill read from the DAS as f64 still matches.
+    pub fn is_fill(&self, fills: &[f64]) -> bool {
+        match self {
+            DataValue::Float32(f) => f.is_nan() || fills.iter().any(|fill| *f == *fill as f32),
+            DataValue::String(_) => false,
+            v => TryInto::<f64>::try_into(v.clone()).is_ok_and(|v| fills.contains(&v)),
+        }
+    }
+
+    /// Converts the value to JSON. Floats are widened through their shortest decimal form, so
+    /// 0.1 is written as 0.1 rather than 0.10000000149011612. NaN and infinite floats have no
+    /// JSON representation and become null.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            DataValue::Byte(b) => serde_json::Value::from(*b as u8),
+            DataValue::Int32(i) => serde_json::Value::from(*i),
+            DataValue::Int64(i) => serde_json::Value::from(*i),
+            DataValue::UInt64(i) => serde_json::Value::from(*i),
+            DataValue::Float32(f) => {
+                serde_json::Number::from_f64(f.to_string().parse().unwrap_or(*f as f64))
+                    .map(serde_json::Value::Number)
+                    .unwrap_or(serde_json::Value::Null)
+            }
+            DataValue::String(s) => serde_json::Value::String(s.clone()),
+        }
+    }
+}
+
+impl TryInto<bool> for DataValue {
+    type Error = Error;
+
+    fn try_into(self) -> Result<bool, Self::Error> {
+        self.try_into_bool()
+    }
+}
+
 impl TryInto<String> for DataValue {
     type Error = Error;
 
@@ -61,7 +156,10 @@ impl TryInto<i32> for DataValue {
 
     fn try_into(self) -> Result<i32, Self::Error> {
         match &self {
+            DataValue::Byte(b) => Ok(*b as u8 as i32),
             DataValue::Int32(i) => Ok(*i),
+            DataValue::Int64(i) => i32::try_from(*i).map_err(|_| Error::InvalidTypecast),
+            DataValue::UInt64(i) => i32::try_from(*i).map_err(|_| Error::InvalidTypecast),
             DataValue::Float32(f) => Ok(*f as i32),
             DataValue::String(_) => Err(Error::InvalidTypecast),
         }
@@ -73,19 +171,40 @@ impl TryInto<i64> for DataValue {
 
     fn try_into(self) -> Result<i64, Self::Error> {
         match &self {
+            DataValue::Byte(b) => Ok(*b as u8 as i64),
             DataValue::Int32(i) => Ok(*i as i64),
+            DataValue::Int64(i) => Ok(*i),
+            DataValue::UInt64(i) => i64::try_from(*i).map_err(|_| Error::InvalidTypecast),
             DataValue::Float32(f) => Ok(*f as i64),
             DataValue::String(_) => Err(Error::InvalidTypecast),
         }
     }
 }
 
+impl TryInto<u64> for DataValue {
+    type Error = Error;
+
+    fn try_into(self) -> Result<u64, Self::Error> {
+        match &self {
+            DataValue::Byte(b) => Ok(*b as u8 as u64),
+            DataValue::Int32(i) => u64::try_from(*i).map_err(|_| Error::InvalidTypecast),
+            DataValue::Int64(i) => u64::try_from(*i).map_err(|_| Error::InvalidTypecast),
+            DataValue::UInt64(i) => Ok(*i),
+            DataValue::Float32(f) => Ok(*f as u64),
+            DataValue::String(_) => Err(Error::InvalidTypecast),
+        }
+    }
+}
+
 impl TryInto<f32> for DataValue {
     type Error = Error;
 
     fn try_into(self) -> Result<f32, Self::Error> {
         match &self {
+            DataValue::Byte(b) => Ok(*b as u8 as f32),
             DataValue::Int32(i) => Ok(*i as f32),
+            DataValue::Int64(i) => Ok(*i as f32),
+            DataValue::UInt64(i) => Ok(*i as f32),
             DataValue::Float32(f) => Ok(*f),
             DataValue::String(_) => Err(Error::InvalidTypecast),
         }
@@ -97,13 +216,41 @@ impl TryInto<f64> for DataValue {
 
     fn try_into(self) -> Result<f64, Self::Error> {
         match &self {
+            DataValue::Byte(b) => Ok(*b as u8 as f64),
             DataValue::Int32(i) => Ok(*i as f64),
+            DataValue::Int64(i) => Ok(*i as f64),
+            DataValue::UInt64(i) => Ok(*i as f64),
             DataValue::Float32(f) => Ok(*f as f64),
             DataValue::String(_) => Err(Error::InvalidTypecast),
         }
     }
 }
 
+/// Reads the duplicated DODS length header, rejecting counts that disagree or that
+/// could not possibly fit in the remaining buffer before anything is allocated.
+fn parse_count_header<'a>(
+    input: &'a [u8],
+    data_type: &DataType,
+) -> Result<(&'a [u8], usize), Error> {
+    let (input, count) =
+        be_u32(input).map_err(|_: nom::Err<nom::error::Error<_>>| Error::ParseError)?;
+    let (input, count_2) =
+        be_u32(input).map_err(|_: nom::Err<nom::error::Error<_>>| Error::ParseError)?;
+
+    if count != count_2 {
+        return Err(Error::CountMismatch);
+    }
+
+    let min_element_size = match data_type {
+        DataType::String => 4,
+        _ => data_type.byte_count(),
+    };
+
+    match (count as usize).checked_mul(min_element_size) {
+        Some(size) if size <= input.len() => Ok((input, count as usize)),
+        _ => Err(Error::CountMismatch),
+    }
+}
 
 pub struct DataValueIterator<'a> {
     input: &'a [u8],
@@ -113,23 +260,28 @@ pub struct DataValueIterator<'a> {
 
 impl<'a> DataValueIterator<'a> {
     pub fn new(data: &'a [u8], data_type: DataType) -> Result<Self, Error> {
-        let (input, count) =
-            be_u32(data).map_err(|_: nom::Err<nom::error::Error<_>>| Error::ParseError)?;
-        let (input, count_2) =
-            be_u32(input).map_err(|_: nom::Err<nom::error::Error<_>>| Error::ParseError)?;
+        let (input, count) = parse_count_header(data, &data_type)?;
 
-        assert!(count == count_2);
+        // Byte arrays are padded to a multiple of four, which must not be read as values
+        let input = match data_type {
+            DataType::String => input,
+            _ => &input[..count * data_type.byte_count()],
+        };
 
         Ok(Self {
             input,
             data_type,
-            count: count as usize,
+            count,
         })
     }
 
     pub fn len(&self) -> usize {
         self.count
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
 }
 
 impl<'a> Iterator for DataValueIterator<'a> {
@@ -141,16 +293,21 @@ impl<'a> Iterator for DataValueIterator<'a> {
         }
 
         let (input, value) = match &self.data_type {
-            DataType::Int32 => {
-                be_i32(self.input)
-                    .map_err(|_: nom::Err<nom::error::Error<_>>| Error::ParseError)
-                    .map_or(None, |(input, i)| Some((input, DataValue::Int32(i))))
-            }
-            DataType::Float32 => {
-                be_f32(self.input)
-                    .map_err(|_: nom::Err<nom::error::Error<_>>| Error::ParseError)
-                    .map_or(None, |(input, f)| Some((input, DataValue::Float32(f))))
-            }
+            DataType::Byte => be_i8(self.input)
+                .map_err(|_: nom::Err<nom::error::Error<_>>| Error::ParseError)
+                .map_or(None, |(input, b)| Some((input, DataValue::Byte(b)))),
+            DataType::Int32 => be_i32(self.input)
+                .map_err(|_: nom::Err<nom::error::Error<_>>| Error::ParseError)
+                .map_or(None, |(input, i)| Some((input, DataValue::Int32(i)))),
+            DataType::Int64 => be_i64(self.input)
+                .map_err(|_: nom::Err<nom::error::Error<_>>| Error::ParseError)
+                .map_or(None, |(input, i)| Some((input, DataValue::Int64(i)))),
+            DataType::UInt64 => be_u64(self.input)
+                .map_err(|_: nom::Err<nom::error::Error<_>>| Error::ParseError)
+                .map_or(None, |(input, i)| Some((input, DataValue::UInt64(i)))),
+            DataType::Float32 => be_f32(self.input)
+                .map_err(|_: nom::Err<nom::error::Error<_>>| Error::ParseError)
+                .map_or(None, |(input, f)| Some((input, DataValue::Float32(f)))),
             DataType::String => unreachable!(),
         }?;
 
@@ -159,29 +316,415 @@ impl<'a> Iterator for DataValueIterator<'a> {
     }
 }
 
+/// Values nested to an arbitrary rank, as JSON oriented consumers expect multidimensional data
+#[derive(Clone, Debug, PartialEq)]
+pub enum NestedF64 {
+    Value(f64),
+    Array(Vec<NestedF64>),
+}
+
+impl NestedF64 {
+    fn from_flat(values: &[f64], shape: &[usize]) -> Self {
+        match shape.split_first() {
+            None => NestedF64::Value(values[0]),
+            Some((dim, rest)) => {
+                let step = values.len().checked_div(*dim).unwrap_or(0);
+                NestedF64::Array(
+                    (0..*dim)
+                        .map(|i| NestedF64::from_flat(&values[i * step..(i + 1) * step], rest))
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+/// A flat array paired with the row-major shape it represents, for N-dimensional indexing
+#[derive(Clone, Debug)]
+pub struct ShapedArray {
+    pub data: DataArray,
+    pub shape: Vec<usize>,
+    pub strides: Vec<usize>,
+}
+
+impl ShapedArray {
+    /// The value at the given indices, or `None` if there is not one index per dimension
+    /// or any index is out of bounds
+    pub fn get(&self, indices: &[usize]) -> Option<DataValue> {
+        if indices.len() != self.shape.len() || indices.iter().zip(&self.shape).any(|(i, s)| i >= s)
+        {
+            return None;
+        }
+
+        let element = indices
+            .iter()
+            .zip(&self.strides)
+            .map(|(i, stride)| i * stride)
+            .sum();
+        self.data.get(element)
+    }
+}
+
+/// Serializes like `DataValue`, with the same caveat for NaN and infinite floats
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DataArray {
+    /// Raw byte bits, read as unsigned by numeric conversions like `DataValue::Byte`
+    Byte(Vec<i8>),
     Int32(Vec<i32>),
+    Int64(Vec<i64>),
+    UInt64(Vec<u64>),
     Float32(Vec<f32>),
 }
 
 impl DataArray {
-    pub fn parse<'a>(input: &'a [u8], data_type: DataType) -> IResult<&'a [u8], Self> {
-        let (input, length) = be_u32(input)?;
-        let (input, length_2) = be_u32(input)?;
+    pub fn from_bytes(input: &[u8], data_type: DataType) -> Result<Self, Error> {
+        let (input, length) = parse_count_header(input, &data_type)?;
+        match Self::parse_values(input, data_type, length, ByteOrder::BigEndian) {
+            Ok((_, a)) => Ok(a),
+            Err(_) => Err(Error::ParseError),
+        }
+    }
 
-        assert!(length == length_2);
+    pub fn parse(input: &[u8], data_type: DataType) -> IResult<&[u8], Self> {
+        let (input, length) = match parse_count_header(input, &data_type) {
+            Ok(r) => r,
+            Err(_) => {
+                return Err(nom::Err::Failure(nom::error::Error::new(
+                    input,
+                    nom::error::ErrorKind::Count,
+                )))
+            }
+        };
 
-        match data_type {
-            DataType::Int32 => {
-                let (input, values) = count(be_i32, length as usize)(input)?;
+        let (input, values) = Self::parse_values(input, data_type, length, ByteOrder::BigEndian)?;
+
+        // DODS pads byte arrays out to a multiple of four bytes
+        let padding = match values {
+            DataArray::Byte(_) => xdr_padding(length),
+            _ => 0,
+        };
+        let (input, _) = nom::bytes::complete::take(padding)(input)?;
+
+        Ok((input, values))
+    }
+
+    pub fn parse_values(
+        input: &[u8],
+        data_type: DataType,
+        length: usize,
+        byte_order: ByteOrder,
+    ) -> IResult<&[u8], Self> {
+        match (data_type, byte_order) {
+            (DataType::Byte, _) => {
+                let (input, values) = count(be_i8, length)(input)?;
+                Ok((input, Self::Byte(values)))
+            }
+            #[cfg(feature = "bytemuck")]
+            (DataType::Int32, ByteOrder::BigEndian) => {
+                let (input, words) = be_words(input, length)?;
+                Ok((input, Self::Int32(bytemuck::cast_vec(words))))
+            }
+            #[cfg(not(feature = "bytemuck"))]
+            (DataType::Int32, ByteOrder::BigEndian) => {
+                let (input, values) = count(be_i32, length)(input)?;
+                Ok((input, Self::Int32(values)))
+            }
+            (DataType::Int32, ByteOrder::LittleEndian) => {
+                let (input, values) = count(le_i32, length)(input)?;
                 Ok((input, Self::Int32(values)))
             }
-            DataType::Float32 => {
-                let (input, values) = count(be_f32, length as usize)(input)?;
+            (DataType::Int64, ByteOrder::BigEndian) => {
+                let (input, values) = count(be_i64, length)(input)?;
+                Ok((input, Self::Int64(values)))
+            }
+            (DataType::Int64, ByteOrder::LittleEndian) => {
+                let (input, values) = count(le_i64, length)(input)?;
+                Ok((input, Self::Int64(values)))
+            }
+            (DataType::UInt64, ByteOrder::BigEndian) => {
+                let (input, values) = count(be_u64, length)(input)?;
+                Ok((input, Self::UInt64(values)))
+            }
+            (DataType::UInt64, ByteOrder::LittleEndian) => {
+                let (input, values) = count(le_u64, length)(input)?;
+                Ok((input, Self::UInt64(values)))
+            }
+            #[cfg(feature = "bytemuck")]
+            (DataType::Float32, ByteOrder::BigEndian) => {
+                let (input, words) = be_words(input, length)?;
+                Ok((input, Self::Float32(bytemuck::cast_vec(words))))
+            }
+            #[cfg(not(feature = "bytemuck"))]
+            (DataType::Float32, ByteOrder::BigEndian) => {
+                let (input, values) = count(be_f32, length)(input)?;
                 Ok((input, Self::Float32(values)))
             }
-            DataType::String => unreachable!(),
+            (DataType::Float32, ByteOrder::LittleEndian) => {
+                let (input, values) = count(le_f32, length)(input)?;
+                Ok((input, Self::Float32(values)))
+            }
+            (DataType::String, _) => unreachable!(),
+        }
+    }
+
+    pub fn data_type(&self) -> DataType {
+        match self {
+            DataArray::Byte(_) => DataType::Byte,
+            DataArray::Int32(_) => DataType::Int32,
+            DataArray::Int64(_) => DataType::Int64,
+            DataArray::UInt64(_) => DataType::UInt64,
+            DataArray::Float32(_) => DataType::Float32,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            DataArray::Byte(v) => v.len(),
+            DataArray::Int32(v) => v.len(),
+            DataArray::Int64(v) => v.len(),
+            DataArray::UInt64(v) => v.len(),
+            DataArray::Float32(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The element at a flat index, or `None` past the end
+    pub fn get(&self, index: usize) -> Option<DataValue> {
+        match self {
+            DataArray::Byte(v) => v.get(index).map(|b| DataValue::Byte(*b)),
+            DataArray::Int32(v) => v.get(index).map(|i| DataValue::Int32(*i)),
+            DataArray::Int64(v) => v.get(index).map(|i| DataValue::Int64(*i)),
+            DataArray::UInt64(v) => v.get(index).map(|i| DataValue::UInt64(*i)),
+            DataArray::Float32(v) => v.get(index).map(|f| DataValue::Float32(*f)),
+        }
+    }
+
+    /// Reorders the row-major values DAP sends into column-major (Fortran) order for the given shape
+    pub fn to_column_major(&self, shape: &[usize]) -> Result<DataArray, Error> {
+        match self {
+            DataArray::Byte(v) => Ok(DataArray::Byte(column_major(v, shape)?)),
+            DataArray::Int32(v) => Ok(DataArray::Int32(column_major(v, shape)?)),
+            DataArray::Int64(v) => Ok(DataArray::Int64(column_major(v, shape)?)),
+            DataArray::UInt64(v) => Ok(DataArray::UInt64(column_major(v, shape)?)),
+            DataArray::Float32(v) => Ok(DataArray::Float32(column_major(v, shape)?)),
+        }
+    }
+
+    /// Reverses the row-major values along one axis of the given shape
+    pub fn flip_axis(&self, shape: &[usize], axis: usize) -> Result<DataArray, Error> {
+        match self {
+            DataArray::Byte(v) => Ok(DataArray::Byte(flip(v, shape, axis)?)),
+            DataArray::Int32(v) => Ok(DataArray::Int32(flip(v, shape, axis)?)),
+            DataArray::Int64(v) => Ok(DataArray::Int64(flip(v, shape, axis)?)),
+            DataArray::UInt64(v) => Ok(DataArray::UInt64(flip(v, shape, axis)?)),
+            DataArray::Float32(v) => Ok(DataArray::Float32(flip(v, shape, axis)?)),
+        }
+    }
+
+    /// Flips the array and its coordinate along `axis` when the coordinate is descending,
+    /// so both come back ascending. Ascending coordinates are returned unchanged.
+    pub fn ascending_along(
+        &self,
+        shape: &[usize],
+        axis: usize,
+        coord: &DataArray,
+    ) -> Result<(DataArray, DataArray), Error> {
+        let values: Vec<f64> = coord.clone().try_into()?;
+        if shape.get(axis) != Some(&values.len()) {
+            return Err(Error::InvalidData);
+        }
+
+        match (values.first(), values.last()) {
+            (Some(first), Some(last)) if first > last => Ok((
+                self.flip_axis(shape, axis)?,
+                coord.flip_axis(&[values.len()], 0)?,
+            )),
+            _ => Ok((self.clone(), coord.clone())),
+        }
+    }
+
+    /// Reshapes the row-major values into nested arrays following the given shape
+    pub fn into_nested_f64(self, shape: &[usize]) -> Result<NestedF64, Error> {
+        let values: Vec<f64> = self.try_into()?;
+        if shape.iter().product::<usize>() != values.len() {
+            return Err(Error::InvalidData);
+        }
+
+        Ok(NestedF64::from_flat(&values, shape))
+    }
+
+    /// Copies the row-major values into an `ndarray` array of the given shape, usually the
+    /// sizes from the DDS array's coords
+    #[cfg(feature = "ndarray")]
+    pub fn to_ndarray(&self, shape: &[usize]) -> Result<ndarray::ArrayD<f64>, Error> {
+        ndarray::ArrayD::from_shape_vec(ndarray::IxDyn(shape), self.values_f64())
+            .map_err(|_| Error::InvalidData)
+    }
+
+    /// Every value converted with `DataValue::to_json`, in row-major order
+    #[cfg(feature = "json")]
+    pub fn to_json_values(&self) -> Vec<serde_json::Value> {
+        (0..self.len())
+            .filter_map(|i| self.get(i))
+            .map(|v| v.to_json())
+            .collect()
+    }
+
+    /// Pairs the row-major values with their shape, usually the DDS dimension sizes
+    pub fn into_shaped(self, dims: &[usize]) -> Result<ShapedArray, Error> {
+        if dims.iter().product::<usize>() != self.len() {
+            return Err(Error::InvalidData);
+        }
+
+        Ok(ShapedArray {
+            strides: strides(dims),
+            shape: dims.to_vec(),
+            data: self,
+        })
+    }
+
+    /// The (min, max, mean) of the array, skipping NaN and the given fill value.
+    /// All three are NaN when no valid values remain.
+    pub fn stats(&self, fill: Option<f64>) -> (f64, f64, f64) {
+        let (min, max, sum, count) = (0..self.len())
+            .filter_map(|i| self.get(i))
+            .filter(|v| !v.is_fill(fill.as_slice()))
+            .filter_map(|v| TryInto::<f64>::try_into(v).ok())
+            .fold(
+                (f64::INFINITY, f64::NEG_INFINITY, 0.0, 0usize),
+                |(min, max, sum, count), v| (min.min(v), max.max(v), sum + v, count + 1),
+            );
+
+        if count == 0 {
+            return (f64::NAN, f64::NAN, f64::NAN);
+        }
+
+        (min, max, sum / count as f64)
+    }
+
+    /// Whether every element is NaN or equal to the fill value, compared at the array's precision.
+    /// An empty array counts as all fill.
+    pub fn is_all_fill(&self, fill: f64) -> bool {
+        (0..self.len())
+            .filter_map(|i| self.get(i))
+            .all(|v| v.is_fill(&[fill]))
+    }
+
+    /// Mean of the row major array weighted by the cosine of latitude, where `lat_axis` is the
+    /// index of the latitude dimension in `shape`, e.g. 1 for `[time][lat][lon]`. NaN values
+    /// are skipped, and NaN is returned if the shapes do not match.
+    pub fn area_weighted_mean(&self, lat_values: &[f64], shape: &[usize], lat_axis: usize) -> f64 {
+        let values = self.values_f64();
+
+        if shape.iter().product::<usize>() != values.len()
+            || shape.get(lat_axis) != Some(&lat_values.len())
+        {
+            return f64::NAN;
+        }
+
+        let stride = shape[lat_axis + 1..].iter().product::<usize>();
+        let weights = lat_values
+            .iter()
+            .map(|lat| lat.to_radians().cos())
+            .collect::<Vec<_>>();
+
+        let (sum, total_weight) = values
+            .into_iter()
+            .enumerate()
+            .filter(|(_, v)| !v.is_nan())
+            .map(|(i, v)| (v, weights[(i / stride) % lat_values.len()]))
+            .fold((0.0, 0.0), |(sum, total), (v, w)| (sum + v * w, total + w));
+
+        sum / total_weight
+    }
+
+    fn values_f64(&self) -> Vec<f64> {
+        match self {
+            DataArray::Byte(v) => v.iter().map(|b| *b as u8 as f64).collect(),
+            DataArray::Int32(v) => v.iter().map(|i| *i as f64).collect(),
+            DataArray::Int64(v) => v.iter().map(|i| *i as f64).collect(),
+            DataArray::UInt64(v) => v.iter().map(|i| *i as f64).collect(),
+            DataArray::Float32(v) => v.iter().map(|f| *f as f64).collect(),
+        }
+    }
+}
+
+/// Copies `length` big endian 4 byte words out in bulk and swaps them to native order in
+/// a single pass, which the compiler vectorizes, instead of decoding value by value
+#[cfg(feature = "bytemuck")]
+fn be_words(input: &[u8], length: usize) -> IResult<&[u8], Vec<u32>> {
+    let (input, bytes) = nom::bytes::complete::take(length.saturating_mul(4))(input)?;
+    let mut words: Vec<u32> = bytemuck::pod_collect_to_vec(bytes);
+    words.iter_mut().for_each(|w| *w = u32::from_be(*w));
+    Ok((input, words))
+}
+
+fn column_major<T: Copy>(values: &[T], shape: &[usize]) -> Result<Vec<T>, Error> {
+    if shape.iter().product::<usize>() != values.len() {
+        return Err(Error::InvalidData);
+    }
+
+    let mut reordered = values.to_vec();
+    values.iter().enumerate().for_each(|(i, value)| {
+        // Peel the row-major index apart from the fastest varying dimension, which
+        // becomes the slowest varying one in column-major order
+        let (_, _, target) =
+            shape
+                .iter()
+                .rev()
+                .fold((i, values.len(), 0), |(remaining, stride, target), dim| {
+                    let stride = stride / dim;
+                    (remaining / dim, stride, target + (remaining % dim) * stride)
+                });
+        reordered[target] = *value;
+    });
+
+    Ok(reordered)
+}
+
+fn flip<T: Copy>(values: &[T], shape: &[usize], axis: usize) -> Result<Vec<T>, Error> {
+    if shape.iter().product::<usize>() != values.len() || axis >= shape.len() {
+        return Err(Error::InvalidData);
+    }
+
+    let dim = shape[axis];
+    let stride = shape[axis + 1..].iter().product::<usize>();
+    Ok((0..values.len())
+        .map(|i| {
+            let k = (i / stride) % dim;
+            values[i - k * stride + (dim - 1 - k) * stride]
+        })
+        .collect())
+}
+
+/// The number of bytes that pad `length` bytes out to the next multiple of four in XDR
+pub(crate) fn xdr_padding(length: usize) -> usize {
+    (4 - length % 4) % 4
+}
+
+impl TryInto<Vec<i8>> for DataArray {
+    type Error = Error;
+
+    fn try_into(self) -> Result<Vec<i8>, Self::Error> {
+        match self {
+            DataArray::Byte(v) => Ok(v),
+            _ => Err(Error::InvalidTypecast),
+        }
+    }
+}
+
+/// Reinterprets each byte as unsigned, as quality flag bitfields are read, so -1 becomes 255
+impl TryInto<Vec<u8>> for DataArray {
+    type Error = Error;
+
+    fn try_into(self) -> Result<Vec<u8>, Self::Error> {
+        match self {
+            DataArray::Byte(v) => Ok(v.into_iter().map(|b| b as u8).collect()),
+            _ => Err(Error::InvalidTypecast),
         }
     }
 }
@@ -191,7 +734,16 @@ impl TryInto<Vec<i32>> for DataArray {
 
     fn try_into(self) -> Result<Vec<i32>, Self::Error> {
         match self {
+            DataArray::Byte(v) => Ok(v.into_iter().map(|b| b as u8 as i32).collect()),
             DataArray::Int32(v) => Ok(v),
+            DataArray::Int64(v) => v
+                .into_iter()
+                .map(|i| i32::try_from(i).map_err(|_| Error::InvalidTypecast))
+                .collect(),
+            DataArray::UInt64(v) => v
+                .into_iter()
+                .map(|i| i32::try_from(i).map_err(|_| Error::InvalidTypecast))
+                .collect(),
             DataArray::Float32(v) => Ok(v.into_iter().map(|i| i as i32).collect()),
         }
     }
@@ -202,7 +754,13 @@ impl TryInto<Vec<i64>> for DataArray {
 
     fn try_into(self) -> Result<Vec<i64>, Self::Error> {
         match self {
+            DataArray::Byte(v) => Ok(v.into_iter().map(|b| b as u8 as i64).collect()),
             DataArray::Int32(v) => Ok(v.into_iter().map(|i| i as i64).collect()),
+            DataArray::Int64(v) => Ok(v),
+            DataArray::UInt64(v) => v
+                .into_iter()
+                .map(|i| i64::try_from(i).map_err(|_| Error::InvalidTypecast))
+                .collect(),
             DataArray::Float32(v) => Ok(v.into_iter().map(|i| i as i64).collect()),
         }
     }
@@ -213,7 +771,10 @@ impl TryInto<Vec<f32>> for DataArray {
 
     fn try_into(self) -> Result<Vec<f32>, Self::Error> {
         match self {
+            DataArray::Byte(v) => Ok(v.into_iter().map(|b| b as u8 as f32).collect()),
             DataArray::Int32(v) => Ok(v.into_iter().map(|i| i as f32).collect()),
+            DataArray::Int64(v) => Ok(v.into_iter().map(|i| i as f32).collect()),
+            DataArray::UInt64(v) => Ok(v.into_iter().map(|i| i as f32).collect()),
             DataArray::Float32(v) => Ok(v),
         }
     }
@@ -223,16 +784,15 @@ impl TryInto<Vec<f64>> for DataArray {
     type Error = Error;
 
     fn try_into(self) -> Result<Vec<f64>, Self::Error> {
-        match self {
-            DataArray::Int32(v) => Ok(v.into_iter().map(|i| i as f64).collect()),
-            DataArray::Float32(v) => Ok(v.into_iter().map(|i| i as f64).collect()),
-        }
+        Ok(self.values_f64())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::DataType;
+    use crate::errors::Error;
+
+    use super::{DataArray, DataType, DataValue, DataValueIterator, NestedF64};
 
     #[test]
     fn parse_data_type() {
@@ -248,4 +808,453 @@ mod tests {
         let (_, dtype) = DataType::parse(input).unwrap();
         assert_eq!(dtype, DataType::String);
     }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn data_value_to_json() {
+        use serde_json::json;
+
+        assert_eq!(DataValue::Byte(-1).to_json(), json!(255));
+        assert_eq!(DataValue::Int32(-7).to_json(), json!(-7));
+        assert_eq!(
+            DataValue::Int64(-5_000_000_000).to_json(),
+            json!(-5_000_000_000i64)
+        );
+        assert_eq!(DataValue::UInt64(u64::MAX).to_json(), json!(u64::MAX));
+        assert_eq!(DataValue::Float32(2.5).to_json(), json!(2.5));
+        assert_eq!(
+            DataValue::String("degC".to_string()).to_json(),
+            json!("degC")
+        );
+        assert_eq!(DataValue::Float32(0.1).to_json(), json!(0.1));
+        assert_eq!(DataValue::Float32(-273.15).to_json(), json!(-273.15));
+        assert_eq!(
+            DataArray::Float32(vec![0.1, 1.7]).to_json_values(),
+            vec![json!(0.1), json!(1.7)]
+        );
+        assert!(DataValue::Float32(f32::NAN).to_json().is_null());
+        assert!(DataValue::Float32(f32::INFINITY).to_json().is_null());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        for data_type in [
+            DataType::Byte,
+            DataType::Int32,
+            DataType::Int64,
+            DataType::UInt64,
+            DataType::Float32,
+            DataType::String,
+        ] {
+            let json = serde_json::to_string(&data_type).unwrap();
+            assert_eq!(serde_json::from_str::<DataType>(&json).unwrap(), data_type);
+        }
+
+        for value in [
+            DataValue::Byte(-3),
+            DataValue::Int32(-4),
+            DataValue::Int64(-5_000_000_000),
+            DataValue::UInt64(u64::MAX),
+            DataValue::Float32(1.0),
+            DataValue::String("degC".to_string()),
+        ] {
+            let json = serde_json::to_string(&value).unwrap();
+            let read = serde_json::from_str::<DataValue>(&json).unwrap();
+            assert_eq!(format!("{read:?}"), format!("{value:?}"));
+        }
+        assert_eq!(
+            serde_json::to_string(&DataValue::Float32(1.0)).unwrap(),
+            r#"{"Float32":1.0}"#
+        );
+
+        for array in [
+            DataArray::Byte(vec![-1, 2]),
+            DataArray::Int32(vec![1, 2]),
+            DataArray::Int64(vec![i64::MIN, 0]),
+            DataArray::UInt64(vec![u64::MAX]),
+            DataArray::Float32(vec![0.5]),
+        ] {
+            let json = serde_json::to_string(&array).unwrap();
+            let read = serde_json::from_str::<DataArray>(&json).unwrap();
+            assert_eq!(format!("{read:?}"), format!("{array:?}"));
+        }
+        assert_eq!(
+            serde_json::to_string(&DataArray::Int32(vec![1, 2])).unwrap(),
+            r#"{"Int32":[1,2]}"#
+        );
+
+        // JSON has no NaN, so it is written as null and cannot be read back
+        let nan = serde_json::to_string(&DataValue::Float32(f32::NAN)).unwrap();
+        assert_eq!(nan, r#"{"Float32":null}"#);
+        assert!(serde_json::from_str::<DataValue>(&nan).is_err());
+    }
+
+    #[test]
+    fn data_type_names() {
+        for data_type in [
+            DataType::Byte,
+            DataType::Int32,
+            DataType::Int64,
+            DataType::UInt64,
+            DataType::Float32,
+            DataType::String,
+        ] {
+            let (rest, parsed) = DataType::parse(data_type.as_str()).unwrap();
+            assert!(rest.is_empty());
+            assert_eq!(parsed, data_type);
+        }
+
+        assert_eq!(DataArray::Int32(vec![1]).data_type(), DataType::Int32);
+        assert_eq!(DataArray::Byte(vec![]).data_type(), DataType::Byte);
+        assert_eq!(DataArray::Int64(vec![]).data_type(), DataType::Int64);
+        assert_eq!(DataArray::UInt64(vec![]).data_type(), DataType::UInt64);
+        assert_eq!(DataArray::Float32(vec![]).data_type(), DataType::Float32);
+    }
+
+    #[test]
+    fn data_value_to_bool() {
+        assert!(!DataValue::Int32(0).try_into_bool().unwrap());
+        assert!(DataValue::Int32(1).try_into_bool().unwrap());
+        assert!(DataValue::Float32(0.5).try_into_bool().unwrap());
+
+        let flag: bool = DataValue::Int32(-1).try_into().unwrap();
+        assert!(flag);
+
+        assert!(DataValue::String("true".to_string())
+            .try_into_bool()
+            .is_err());
+    }
+
+    #[test]
+    fn parse_byte_array() {
+        let input = [0, 0, 0, 3, 0, 0, 0, 3, 0x01, 0xff, 0x80, 0, 0xaa];
+
+        let (rest, data) = DataArray::parse(&input, DataType::Byte).unwrap();
+        assert_eq!(rest, &[0xaa]);
+        assert!(matches!(&data, DataArray::Byte(v) if v == &vec![1, -1, -128]));
+
+        let flags: Vec<u8> = data.clone().try_into().unwrap();
+        assert_eq!(flags, vec![1, 255, 128]);
+        let signed: Vec<i8> = data.clone().try_into().unwrap();
+        assert_eq!(signed, vec![1, -1, -128]);
+
+        let values = DataValueIterator::new(&input[..12], DataType::Byte)
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(values.len(), 3);
+        assert!(matches!(values[1], DataValue::Byte(-1)));
+
+        // Bytes above 127 widen as unsigned values
+        let widened: Vec<f64> = data.clone().try_into().unwrap();
+        assert_eq!(widened, vec![1.0, 255.0, 128.0]);
+        let widened: Vec<i32> = data.clone().try_into().unwrap();
+        assert_eq!(widened, vec![1, 255, 128]);
+        let value: f64 = DataValue::Byte(-1).try_into().unwrap();
+        assert_eq!(value, 255.0);
+        let value: u64 = DataValue::Byte(-128).try_into().unwrap();
+        assert_eq!(value, 128);
+        assert!(DataArray::Byte(vec![-1, -1]).is_all_fill(255.0));
+        assert_eq!(data.stats(None), (1.0, 255.0, 128.0));
+
+        assert!(TryInto::<Vec<u8>>::try_into(DataArray::Int32(vec![1])).is_err());
+        assert!(TryInto::<Vec<i8>>::try_into(DataArray::Float32(vec![1.0])).is_err());
+    }
+
+    #[test]
+    fn parse_64_bit_arrays() {
+        let mut input = vec![0, 0, 0, 2, 0, 0, 0, 2];
+        input.extend_from_slice(&(-4_000_000_000i64).to_be_bytes());
+        input.extend_from_slice(&1_700_000_000_000_000_000i64.to_be_bytes());
+
+        let data = DataArray::from_bytes(&input, DataType::Int64).unwrap();
+        assert!(
+            matches!(&data, DataArray::Int64(v) if v == &vec![-4_000_000_000, 1_700_000_000_000_000_000])
+        );
+        assert!(matches!(
+            data.get(1),
+            Some(DataValue::Int64(1_700_000_000_000_000_000))
+        ));
+
+        let values: Vec<f64> = data.clone().try_into().unwrap();
+        assert_eq!(values[0], -4e9);
+        assert!(TryInto::<Vec<i32>>::try_into(data).is_err());
+
+        let values = DataValueIterator::new(&input, DataType::Int64)
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert!(matches!(values[0], DataValue::Int64(-4_000_000_000)));
+
+        let mut input = vec![0, 0, 0, 1, 0, 0, 0, 1];
+        input.extend_from_slice(&u64::MAX.to_be_bytes());
+        let data = DataArray::from_bytes(&input, DataType::UInt64).unwrap();
+        assert!(matches!(data.get(0), Some(DataValue::UInt64(u64::MAX))));
+        assert!(TryInto::<Vec<i64>>::try_into(data).is_err());
+
+        let value: i64 = DataValue::UInt64(42).try_into().unwrap();
+        assert_eq!(value, 42);
+        let value: f64 = DataValue::Int64(-7).try_into().unwrap();
+        assert_eq!(value, -7.0);
+
+        // Only one 8 byte value remains, so a count of 2 is rejected up front
+        let input = [0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 1];
+        assert!(matches!(
+            DataArray::from_bytes(&input, DataType::Int64),
+            Err(Error::CountMismatch)
+        ));
+    }
+
+    #[test]
+    fn reject_oversized_count() {
+        let input = [
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x01,
+        ];
+
+        assert!(matches!(
+            DataArray::from_bytes(&input, DataType::Int32),
+            Err(Error::CountMismatch)
+        ));
+        assert!(matches!(
+            DataValueIterator::new(&input, DataType::String),
+            Err(Error::CountMismatch)
+        ));
+        assert!(DataArray::parse(&input, DataType::Float32).is_err());
+    }
+
+    #[test]
+    fn reject_mismatched_counts() {
+        let input = [
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01,
+        ];
+
+        assert!(matches!(
+            DataArray::from_bytes(&input, DataType::Int32),
+            Err(Error::CountMismatch)
+        ));
+    }
+
+    #[test]
+    fn data_array_stats() {
+        let data = DataArray::Float32(vec![1.0, 999.0, 3.0, f32::NAN, 8.0]);
+
+        let (min, max, mean) = data.stats(Some(999.0));
+        assert_eq!(min, 1.0);
+        assert_eq!(max, 8.0);
+        assert!((mean - 4.0).abs() < 0.0001);
+
+        let (min, max, mean) = data.stats(None);
+        assert_eq!(min, 1.0);
+        assert_eq!(max, 999.0);
+        assert!((mean - 252.75).abs() < 0.0001);
+
+        let (min, _, _) = DataArray::Float32(vec![999.0]).stats(Some(999.0));
+        assert!(min.is_nan());
+    }
+
+    #[test]
+    fn data_array_len_and_get() {
+        let ints = DataArray::Int32(vec![3, -1]);
+        assert_eq!(ints.len(), 2);
+        assert!(!ints.is_empty());
+        assert!(matches!(ints.get(1), Some(DataValue::Int32(-1))));
+        assert!(ints.get(2).is_none());
+
+        let floats = DataArray::Float32(vec![0.5]);
+        assert_eq!(floats.len(), 1);
+        assert!(matches!(floats.get(0), Some(DataValue::Float32(f)) if f == 0.5));
+
+        let empty = DataArray::Float32(vec![]);
+        assert!(empty.is_empty());
+        assert!(empty.get(0).is_none());
+    }
+
+    #[test]
+    fn data_array_is_all_fill() {
+        assert!(DataArray::Float32(vec![9.96921e36, f32::NAN, 9.96921e36]).is_all_fill(9.96921e36));
+        assert!(!DataArray::Float32(vec![9.96921e36, 1.0]).is_all_fill(9.96921e36));
+        assert!(DataArray::Int32(vec![-999, -999]).is_all_fill(-999.0));
+        assert!(!DataArray::Int32(vec![-999, 0]).is_all_fill(-999.0));
+    }
+
+    #[test]
+    fn data_array_area_weighted_mean() {
+        let data = DataArray::Float32(vec![1.0, 1.0, 2.0, 2.0, 4.0, f32::NAN]);
+        let lats = [0.0, 60.0, 90.0];
+
+        // Weights are 1, 0.5, and ~0 so the pole row drops out
+        let mean = data.area_weighted_mean(&lats, &[3, 2], 0);
+        assert!((mean - 4.0 / 3.0).abs() < 0.0001);
+
+        let mean = DataArray::Int32(vec![2, 5]).area_weighted_mean(&[0.0, 60.0], &[2], 0);
+        assert!((mean - 3.0).abs() < 0.0001);
+
+        // With equal dimension lengths the axis decides which one is weighted
+        let data = DataArray::Int32(vec![1, 2, 3, 4]);
+        let mean = data.area_weighted_mean(&[0.0, 60.0], &[2, 2], 1);
+        assert!((mean - 7.0 / 3.0).abs() < 0.0001);
+        let mean = data.area_weighted_mean(&[0.0, 60.0], &[2, 2], 0);
+        assert!((mean - 6.5 / 3.0).abs() < 0.0001);
+
+        assert!(data.area_weighted_mean(&lats, &[2, 2], 0).is_nan());
+        assert!(data.area_weighted_mean(&[0.0, 60.0], &[2, 2], 2).is_nan());
+    }
+
+    #[test]
+    fn data_array_flip_axis() {
+        let data = DataArray::Int32(vec![1, 2, 3, 4, 5, 6]);
+
+        let flipped: Vec<i32> = data.flip_axis(&[2, 3], 0).unwrap().try_into().unwrap();
+        assert_eq!(flipped, vec![4, 5, 6, 1, 2, 3]);
+
+        let flipped: Vec<i32> = data.flip_axis(&[2, 3], 1).unwrap().try_into().unwrap();
+        assert_eq!(flipped, vec![3, 2, 1, 6, 5, 4]);
+
+        assert!(data.flip_axis(&[2, 3], 2).is_err());
+        assert!(data.flip_axis(&[4, 2], 0).is_err());
+
+        let lat = DataArray::Float32(vec![45.0, 40.0]);
+        let (data, lat) = data.ascending_along(&[2, 3], 0, &lat).unwrap();
+        let data: Vec<i32> = data.try_into().unwrap();
+        let lat: Vec<f32> = lat.try_into().unwrap();
+        assert_eq!(data, vec![4, 5, 6, 1, 2, 3]);
+        assert_eq!(lat, vec![40.0, 45.0]);
+
+        let (unchanged, _) = DataArray::Int32(vec![1, 2, 3, 4, 5, 6])
+            .ascending_along(&[2, 3], 0, &DataArray::Float32(vec![40.0, 45.0]))
+            .unwrap();
+        let unchanged: Vec<i32> = unchanged.try_into().unwrap();
+        assert_eq!(unchanged, vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn data_array_column_major() {
+        let data = DataArray::Int32(vec![1, 2, 3, 4, 5, 6]);
+
+        let reordered: Vec<i32> = data.to_column_major(&[2, 3]).unwrap().try_into().unwrap();
+        assert_eq!(reordered, vec![1, 4, 2, 5, 3, 6]);
+
+        assert!(data.to_column_major(&[4, 2]).is_err());
+    }
+
+    #[test]
+    fn data_array_nested() {
+        let data = DataArray::Int32(vec![1, 2, 3, 4, 5, 6]);
+
+        let nested = data.clone().into_nested_f64(&[2, 3]).unwrap();
+        let row =
+            |values: [f64; 3]| NestedF64::Array(values.into_iter().map(NestedF64::Value).collect());
+        assert_eq!(
+            nested,
+            NestedF64::Array(vec![row([1.0, 2.0, 3.0]), row([4.0, 5.0, 6.0])])
+        );
+
+        assert!(data.into_nested_f64(&[4, 2]).is_err());
+
+        let empty = DataArray::Float32(vec![]).into_nested_f64(&[2, 0]).unwrap();
+        assert_eq!(
+            empty,
+            NestedF64::Array(vec![NestedF64::Array(vec![]), NestedF64::Array(vec![])])
+        );
+    }
+
+    #[test]
+    fn data_array_into_shaped() {
+        let shaped = DataArray::Int32(vec![1, 2, 3, 4, 5, 6])
+            .into_shaped(&[2, 3])
+            .unwrap();
+        assert_eq!(shaped.shape, vec![2, 3]);
+        assert_eq!(shaped.strides, vec![3, 1]);
+
+        assert!(matches!(shaped.get(&[0, 0]), Some(DataValue::Int32(1))));
+        assert!(matches!(shaped.get(&[0, 2]), Some(DataValue::Int32(3))));
+        assert!(matches!(shaped.get(&[1, 0]), Some(DataValue::Int32(4))));
+        assert!(matches!(shaped.get(&[1, 2]), Some(DataValue::Int32(6))));
+        assert!(shaped.get(&[2, 0]).is_none());
+        assert!(shaped.get(&[0, 3]).is_none());
+        assert!(shaped.get(&[1]).is_none());
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn data_array_to_ndarray() {
+        use crate::dds::{DdsDatasetBuilder, DdsValue};
+
+        let dds = DdsDatasetBuilder::new("grid.nc")
+            .add_grid(
+                "temperature",
+                DataType::Int32,
+                &[("time", 2), ("lat", 3), ("lon", 2)],
+                &[DataType::Int32, DataType::Float32, DataType::Float32],
+            )
+            .build()
+            .unwrap();
+        let grid = match &dds.values[0] {
+            DdsValue::Grid(g) => g,
+            DdsValue::Array(_) => unreachable!(),
+        };
+
+        let mut input = vec![0, 0, 0, 12, 0, 0, 0, 12];
+        (0..12i32).for_each(|i| input.extend_from_slice(&i.to_be_bytes()));
+        assert_eq!(input.len(), grid.array.byte_count());
+
+        let shape = grid
+            .array
+            .coords
+            .iter()
+            .map(|c| c.1 as usize)
+            .collect::<Vec<_>>();
+        let data = DataArray::from_bytes(&input, grid.array.data_type.clone()).unwrap();
+        let array = data.to_ndarray(&shape).unwrap();
+
+        assert_eq!(array.shape(), &[2, 3, 2]);
+        assert_eq!(array[[0, 0, 1]], 1.0);
+        assert_eq!(array[[0, 2, 0]], 4.0);
+        assert_eq!(array[[1, 0, 0]], 6.0);
+        assert_eq!(array[[1, 2, 1]], 11.0);
+        assert_eq!(array.sum(), 66.0);
+
+        assert!(matches!(data.to_ndarray(&[2, 3]), Err(Error::InvalidData)));
+    }
+
+    #[test]
+    fn data_array_into_shaped_mismatch() {
+        let result = DataArray::Float32(vec![1.0, 2.0, 3.0, 4.0, 5.0]).into_shaped(&[2, 3]);
+        assert!(matches!(result, Err(Error::InvalidData)));
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn bulk_decode_matches_scalar() {
+        use nom::{
+            multi::count,
+            number::complete::{be_f32, be_i32},
+        };
+
+        use super::ByteOrder;
+
+        // A small LCG keeps the input pseudo random without pulling in another dependency
+        let mut state = 0x2545f491u32;
+        let input = (0..4096 * 4)
+            .map(|_| {
+                state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                (state >> 24) as u8
+            })
+            .collect::<Vec<u8>>();
+
+        let (_, scalar) = count(be_i32::<_, nom::error::Error<_>>, 4096)(&input[..]).unwrap();
+        let (_, bulk) =
+            DataArray::parse_values(&input, DataType::Int32, 4096, ByteOrder::BigEndian).unwrap();
+        assert!(matches!(bulk, DataArray::Int32(v) if v == scalar));
+
+        let (_, scalar) = count(be_f32::<_, nom::error::Error<_>>, 4096)(&input[..]).unwrap();
+        let (_, bulk) =
+            DataArray::parse_values(&input, DataType::Float32, 4096, ByteOrder::BigEndian).unwrap();
+        let DataArray::Float32(bulk) = bulk else {
+            panic!("expected Float32 data");
+        };
+        assert!(bulk
+            .iter()
+            .zip(scalar.iter())
+            .all(|(a, b)| a.to_bits() == b.to_bits()));
+    }
 }
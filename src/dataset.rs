@@ -0,0 +1,829 @@
+use std::collections::HashMap;
+
+use crate::{
+    das::{parse_das_attributes, DasAttribute, DasAttributes, DasVariable},
+    data::{DataType, DataValue},
+    dds::{DdsArray, DdsDataset, DdsValue},
+    dods::DodsDataset,
+    errors::Error,
+};
+
+/// The direction of increasing values along a vertical coordinate, from its CF `positive` attribute
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ZDirection {
+    Up,
+    Down,
+}
+
+/// The metadata of an OpenDAP dataset, pairing the DDS structure with its DAS attributes
+#[derive(Clone, Debug)]
+pub struct Dataset {
+    pub dds: DdsDataset,
+    pub das: DasAttributes,
+}
+
+impl Dataset {
+    pub fn new(dds: DdsDataset, das: DasAttributes) -> Self {
+        Dataset { dds, das }
+    }
+
+    pub fn from_bytes(dds: &str, das: &str) -> Result<Self, Error> {
+        let dds = DdsDataset::from_bytes(dds)?;
+        let das = parse_das_attributes(das)?;
+        Ok(Dataset { dds, das })
+    }
+
+    /// The `long_name` attribute of a variable for display, falling back to the variable name
+    pub fn display_name(&self, var: &str) -> String {
+        self.das
+            .get(var)
+            .and_then(|attrs| attrs.get("long_name"))
+            .and_then(|attr| attr.value.clone().try_into().ok())
+            .unwrap_or_else(|| var.to_string())
+    }
+
+    /// A numeric attribute of a variable as f64, whatever its declared type
+    pub fn attribute_f64(&self, var: &str, attr: &str) -> Option<f64> {
+        self.das.get(var)?.get(attr)?.value.clone().try_into().ok()
+    }
+
+    /// The constraint expression selecting the entire variable, e.g. `var[0:6][0:63]`
+    pub fn full_constraint(&self, var: &str) -> Option<String> {
+        let ranges = self
+            .dds
+            .variable(var)?
+            .shape()
+            .iter()
+            .map(|size| format!("[0:{}]", size.saturating_sub(1)))
+            .collect::<String>();
+        Some(format!("{var}{ranges}"))
+    }
+
+    /// The storage chunk shape of a variable from its `_ChunkSizes` attribute
+    pub fn chunk_sizes(&self, var: &str) -> Option<Vec<usize>> {
+        self.das
+            .get(var)?
+            .get("_ChunkSizes")?
+            .values
+            .iter()
+            .map(|v| {
+                let size: i32 = v.clone().try_into().ok()?;
+                usize::try_from(size).ok()
+            })
+            .collect()
+    }
+
+    /// The name of the cell bounds variable for a coordinate, from its CF `bounds` attribute
+    pub fn coordinate_bounds(&self, coord: &str) -> Option<String> {
+        self.das
+            .get(coord)?
+            .get("bounds")?
+            .value
+            .clone()
+            .try_into()
+            .ok()
+    }
+
+    /// Every sentinel marking missing data in a variable: its `_FillValue` followed by each
+    /// entry of the older `missing_value` attribute
+    pub fn fill_values(&self, var: &str) -> Vec<DataValue> {
+        let attrs = match self.das.get(var) {
+            Some(a) => a,
+            None => return Vec::new(),
+        };
+
+        ["_FillValue", "missing_value"]
+            .iter()
+            .filter_map(|name| attrs.get(*name))
+            .flat_map(|attr| attr.values.iter().cloned())
+            .collect()
+    }
+
+    /// The `dim: method` pairs of a variable's CF `cell_methods` attribute. Names listed together,
+    /// as in `lat: lon: mean`, share the method that follows them, and parenthesized comments are
+    /// dropped.
+    pub fn cell_methods(&self, var: &str) -> Vec<(String, String)> {
+        let value: String = match self
+            .das
+            .get(var)
+            .and_then(|attrs| attrs.get("cell_methods"))
+            .and_then(|attr| attr.value.clone().try_into().ok())
+        {
+            Some(v) => v,
+            None => return Vec::new(),
+        };
+
+        let mut stripped = String::with_capacity(value.len());
+        let mut depth = 0usize;
+        for c in value.chars() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                c if depth == 0 => stripped.push(c),
+                _ => {}
+            }
+        }
+
+        let mut methods = Vec::new();
+        let mut names: Vec<&str> = Vec::new();
+        let mut words: Vec<&str> = Vec::new();
+        let mut flush = |names: &mut Vec<&str>, words: &mut Vec<&str>| {
+            let method = words.join(" ");
+            names
+                .drain(..)
+                .for_each(|name| methods.push((name.to_string(), method.clone())));
+            words.clear();
+        };
+
+        for token in stripped.split_whitespace() {
+            match token.strip_suffix(':') {
+                Some(name) if !name.is_empty() => {
+                    if !words.is_empty() {
+                        flush(&mut names, &mut words);
+                    }
+                    names.push(name);
+                }
+                _ => words.push(token),
+            }
+        }
+        flush(&mut names, &mut words);
+
+        methods
+    }
+
+    /// The attributes of the CRS variable referenced by a variable's CF `grid_mapping` attribute
+    pub fn grid_mapping(&self, var: &str) -> Option<&DasVariable> {
+        let mapping: String = self
+            .das
+            .get(var)?
+            .get("grid_mapping")?
+            .value
+            .clone()
+            .try_into()
+            .ok()?;
+        self.das.get(mapping.trim())
+    }
+
+    /// The indices of the grid cell nearest a point, keyed by the latitude and longitude
+    /// dimension names of a variable. Descending axes are handled, and longitudes are compared
+    /// around the circle so -70 finds 290 on a 0 to 360 grid.
+    pub fn nearest_cell(
+        &self,
+        dods: &DodsDataset,
+        var: &str,
+        lat: f64,
+        lon: f64,
+    ) -> Result<HashMap<String, usize>, Error> {
+        let coords = match self.dds.variable(var).ok_or(Error::ParseError)? {
+            DdsValue::Grid(_) => dods.variable_coords(var)?,
+            DdsValue::Array(a) => a
+                .coords
+                .iter()
+                .map(|c| Ok((c.0.clone(), dods.variable_data(&c.0)?)))
+                .collect::<Result<Vec<_>, Error>>()?,
+        };
+
+        let mut cell = HashMap::new();
+        for (name, values) in coords {
+            let (target, distance): (f64, fn(f64, f64) -> f64) = if self.is_latitude(&name) {
+                (lat, |a, b| (a - b).abs())
+            } else if self.is_longitude(&name) {
+                (lon, |a, b| {
+                    180.0 - ((a - b).rem_euclid(360.0) - 180.0).abs()
+                })
+            } else {
+                continue;
+            };
+
+            let values: Vec<f64> = values.try_into()?;
+            let nearest = values
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| !v.is_nan())
+                .min_by(|a, b| distance(*a.1, target).total_cmp(&distance(*b.1, target)))
+                .map(|(i, _)| i)
+                .ok_or(Error::InvalidData)?;
+            cell.insert(name, nearest);
+        }
+
+        if cell.len() != 2 {
+            return Err(Error::InvalidData);
+        }
+
+        Ok(cell)
+    }
+
+    fn is_latitude(&self, coord: &str) -> bool {
+        self.is_axis(
+            coord,
+            &["lat", "latitude"],
+            &["degrees_north", "degree_north", "degrees_N"],
+        )
+    }
+
+    fn is_longitude(&self, coord: &str) -> bool {
+        self.is_axis(
+            coord,
+            &["lon", "longitude"],
+            &["degrees_east", "degree_east", "degrees_E"],
+        )
+    }
+
+    /// Recognizes a coordinate by its name, CF `standard_name`, or `units`
+    fn is_axis(&self, coord: &str, names: &[&str], units: &[&str]) -> bool {
+        let attr = |name: &str| -> Option<String> {
+            self.das
+                .get(coord)?
+                .get(name)?
+                .value
+                .clone()
+                .try_into()
+                .ok()
+        };
+
+        names.iter().any(|n| coord.eq_ignore_ascii_case(n))
+            || attr("standard_name").is_some_and(|s| names.contains(&s.as_str()))
+            || attr("units").is_some_and(|u| units.contains(&u.as_str()))
+    }
+
+    /// The vertical coordinate variable and its direction, found from an `axis "Z"` or
+    /// `positive` attribute. A vertical axis without `positive` is assumed to point up.
+    pub fn vertical_coordinate(&self) -> Option<(String, ZDirection)> {
+        self.dds
+            .coordinate_variables()
+            .into_iter()
+            .find_map(|coord| {
+                let attrs = self.das.get(&coord)?;
+                let string_attr = |name: &str| -> Option<String> {
+                    attrs.get(name)?.value.clone().try_into().ok()
+                };
+
+                let positive = string_attr("positive");
+                let is_z = string_attr("axis").is_some_and(|a| a.eq_ignore_ascii_case("Z"));
+                if !is_z && positive.is_none() {
+                    return None;
+                }
+
+                let direction = match positive {
+                    Some(p) if p.eq_ignore_ascii_case("down") => ZDirection::Down,
+                    _ => ZDirection::Up,
+                };
+                Some((coord, direction))
+            })
+    }
+
+    /// Renders the metadata as a netCDF CDL header, matching the output of `ncdump -h`
+    pub fn to_cdl(&self) -> String {
+        let name = self
+            .dds
+            .name
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .next()
+            .unwrap_or_default();
+
+        let mut cdl = format!("netcdf {name} {{\n");
+
+        cdl.push_str("dimensions:\n");
+        self.dds
+            .dimensions_ordered()
+            .iter()
+            .for_each(|(name, size)| cdl.push_str(&format!("\t{name} = {size} ;\n")));
+
+        cdl.push_str("variables:\n");
+        self.arrays().iter().for_each(|a| {
+            let dims = a
+                .coords
+                .iter()
+                .map(|c| c.0.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            cdl.push_str(&format!(
+                "\t{} {}({dims}) ;\n",
+                cdl_type(&a.data_type),
+                a.name
+            ));
+
+            if let Some(attrs) = self.das.get(&a.name) {
+                sorted_attributes(attrs.values()).iter().for_each(|attr| {
+                    cdl.push_str(&format!(
+                        "\t\t{}:{} = {} ;\n",
+                        a.name,
+                        attr.name,
+                        cdl_values(&attr.values)
+                    ))
+                });
+            }
+        });
+
+        if let Some(attrs) = self.das.get("NC_GLOBAL") {
+            cdl.push_str("\n// global attributes:\n");
+            sorted_attributes(attrs.values()).iter().for_each(|attr| {
+                cdl.push_str(&format!(
+                    "\t\t:{} = {} ;\n",
+                    attr.name,
+                    cdl_values(&attr.values)
+                ))
+            });
+        }
+
+        cdl.push_str("}\n");
+        cdl
+    }
+
+    /// Exports a grid variable from a DODS response as a CoverageJSON coverage. Longitude,
+    /// latitude, and vertical maps become the `x`, `y`, and `z` axes; any other map keeps its
+    /// own name, since the `t` axis requires ISO 8601 strings. Fill and missing values are
+    /// written as null.
+    #[cfg(feature = "json")]
+    pub fn to_coveragejson(
+        &self,
+        dods: &DodsDataset,
+        grid_name: &str,
+    ) -> Result<serde_json::Value, Error> {
+        use serde_json::{json, Map, Value};
+
+        let grid = dods
+            .dds
+            .variable(grid_name)
+            .ok_or(Error::ParseError)?
+            .grid()?;
+
+        let coords = dods.variable_coords(grid_name)?;
+        let axis_names = coords
+            .iter()
+            .map(|(name, _)| covjson_axis(name))
+            .collect::<Vec<_>>();
+
+        let mut axes = Map::new();
+        for ((_, values), axis) in coords.into_iter().zip(axis_names.iter()) {
+            axes.insert(axis.clone(), json!({ "values": values.to_json_values() }));
+        }
+
+        let fills = self
+            .fill_values(grid_name)
+            .into_iter()
+            .filter_map(|v| TryInto::<f64>::try_into(v).ok())
+            .collect::<Vec<_>>();
+
+        let data = dods.variable_data(grid_name)?;
+        let data_type = match data.data_type() {
+            DataType::Float32 => "float",
+            _ => "integer",
+        };
+        let values = (0..data.len())
+            .filter_map(|i| data.get(i))
+            .map(|v| {
+                if v.is_fill(&fills) {
+                    Value::Null
+                } else {
+                    v.to_json()
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let label = self.display_name(grid_name);
+        let mut parameter = json!({
+            "type": "Parameter",
+            "description": { "en": label },
+            "observedProperty": { "label": { "en": label } },
+        });
+        if let Some(units) = self
+            .das
+            .get(grid_name)
+            .and_then(|attrs| attrs.get("units"))
+            .and_then(|attr| TryInto::<String>::try_into(attr.value.clone()).ok())
+        {
+            parameter["unit"] = json!({ "symbol": units });
+        }
+
+        Ok(json!({
+            "type": "Coverage",
+            "domain": {
+                "type": "Domain",
+                "domainType": "Grid",
+                "axes": axes,
+            },
+            "parameters": { grid_name: parameter },
+            "ranges": {
+                grid_name: {
+                    "type": "NdArray",
+                    "dataType": data_type,
+                    "axisNames": axis_names,
+                    "shape": grid.array.coords.iter().map(|c| c.1).collect::<Vec<_>>(),
+                    "values": values,
+                }
+            },
+        }))
+    }
+
+    /// Every array in the dataset in declaration order, including grid maps, without duplicates
+    fn arrays(&self) -> Vec<&DdsArray> {
+        let mut arrays: Vec<&DdsArray> = Vec::new();
+        self.dds.values.iter().for_each(|v| {
+            let members = match v {
+                DdsValue::Array(a) => vec![a],
+                DdsValue::Grid(g) => std::iter::once(&g.array).chain(g.coords.iter()).collect(),
+            };
+            members.into_iter().for_each(|a| {
+                if !arrays.iter().any(|existing| existing.name == a.name) {
+                    arrays.push(a);
+                }
+            });
+        });
+        arrays
+    }
+}
+
+#[cfg(feature = "json")]
+fn covjson_axis(name: &str) -> String {
+    match name.to_lowercase().as_str() {
+        "lon" | "longitude" | "x" => "x".to_string(),
+        "lat" | "latitude" | "y" => "y".to_string(),
+        "depth" | "level" | "altitude" | "height" | "z" => "z".to_string(),
+        _ => name.to_string(),
+    }
+}
+
+fn sorted_attributes<'a>(attrs: impl Iterator<Item = &'a DasAttribute>) -> Vec<&'a DasAttribute> {
+    let mut attrs = attrs.collect::<Vec<_>>();
+    attrs.sort_by(|a, b| a.name.cmp(&b.name));
+    attrs
+}
+
+fn cdl_type(data_type: &DataType) -> &'static str {
+    match data_type {
+        DataType::Byte => "ubyte",
+        DataType::Int32 => "int",
+        DataType::Int64 => "int64",
+        DataType::UInt64 => "uint64",
+        DataType::Float32 => "float",
+        DataType::String => "string",
+    }
+}
+
+/// All the values of an attribute, comma separated as ncdump writes lists
+fn cdl_values(values: &[DataValue]) -> String {
+    values.iter().map(cdl_value).collect::<Vec<_>>().join(", ")
+}
+
+fn cdl_value(value: &DataValue) -> String {
+    match value {
+        DataValue::Byte(b) => format!("{}UB", *b as u8),
+        DataValue::Int32(i) => i.to_string(),
+        DataValue::Int64(i) => format!("{i}LL"),
+        DataValue::UInt64(i) => format!("{i}ULL"),
+        DataValue::Float32(f) => format!("{f:?}f"),
+        DataValue::String(s) => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        das::parse_das_attributes,
+        data::{DataArray, DataType, DataValue},
+        dds::DdsDatasetBuilder,
+        dods::{DodsDataset, DodsEncoder},
+        errors::Error,
+    };
+
+    use super::{Dataset, ZDirection};
+
+    const DDS: &str = r#"Dataset {
+    Int32 time[time = 4];
+    Int32 time_bnds[time = 4][nv = 2];
+    Float32 latitude[latitude = 3];
+    Grid {
+     ARRAY:
+        Float32 temperature[time = 4][latitude = 3];
+     MAPS:
+        Int32 time[time = 4];
+        Float32 latitude[latitude = 3];
+    } temperature;
+} sample.nc;
+"#;
+
+    #[test]
+    fn coordinate_bounds() {
+        let das = r#"Attributes {
+    time {
+        String units "days since 2000-01-01";
+        String bounds "time_bnds";
+    }
+    latitude {
+        String units "degrees_north";
+    }
+}"#;
+        let dataset = Dataset::from_bytes(DDS, das).unwrap();
+
+        assert_eq!(
+            dataset.coordinate_bounds("time"),
+            Some("time_bnds".to_string())
+        );
+        assert_eq!(dataset.coordinate_bounds("latitude"), None);
+        assert_eq!(dataset.coordinate_bounds("missing"), None);
+    }
+
+    #[test]
+    fn vertical_coordinate() {
+        let dds = r#"Dataset {
+    Float32 depth[depth = 2];
+    Float32 latitude[latitude = 3];
+    Grid {
+     ARRAY:
+        Float32 salinity[depth = 2][latitude = 3];
+     MAPS:
+        Float32 depth[depth = 2];
+        Float32 latitude[latitude = 3];
+    } salinity;
+} ocean.nc;
+"#;
+        let das = r#"Attributes {
+    depth {
+        String units "m";
+        String positive "down";
+    }
+    latitude {
+        String axis "Y";
+    }
+}"#;
+        let dataset = Dataset::from_bytes(dds, das).unwrap();
+        assert_eq!(
+            dataset.vertical_coordinate(),
+            Some(("depth".to_string(), ZDirection::Down))
+        );
+
+        let das = r#"Attributes {
+    latitude {
+        String axis "Y";
+    }
+}"#;
+        let dataset = Dataset::from_bytes(DDS, das).unwrap();
+        assert_eq!(dataset.vertical_coordinate(), None);
+    }
+
+    #[test]
+    fn cell_methods() {
+        let das = r#"Attributes {
+    temperature {
+        String cell_methods "time: mean area: maximum";
+    }
+    latitude {
+        String cell_methods "latitude: time: mean (interval: 1 hr) where sea";
+    }
+}"#;
+        let dataset = Dataset::from_bytes(DDS, das).unwrap();
+
+        assert_eq!(
+            dataset.cell_methods("temperature"),
+            vec![
+                ("time".to_string(), "mean".to_string()),
+                ("area".to_string(), "maximum".to_string()),
+            ]
+        );
+        assert_eq!(
+            dataset.cell_methods("latitude"),
+            vec![
+                ("latitude".to_string(), "mean where sea".to_string()),
+                ("time".to_string(), "mean where sea".to_string()),
+            ]
+        );
+        assert!(dataset.cell_methods("time").is_empty());
+    }
+
+    #[test]
+    fn attribute_f64() {
+        let das = r#"Attributes {
+    temperature {
+        Int32 _FillValue -999;
+        Float32 add_offset 273.15;
+        Byte valid_max 255;
+        String units "K";
+    }
+}"#;
+        let dataset = Dataset::from_bytes(DDS, das).unwrap();
+
+        assert_eq!(
+            dataset.attribute_f64("temperature", "_FillValue"),
+            Some(-999.0)
+        );
+        let offset = dataset.attribute_f64("temperature", "add_offset").unwrap();
+        assert!((offset - 273.15).abs() < 0.0001);
+        assert_eq!(dataset.attribute_f64("temperature", "units"), None);
+        assert_eq!(dataset.attribute_f64("temperature", "scale_factor"), None);
+
+        // DAP2 bytes are unsigned
+        assert_eq!(
+            dataset.attribute_f64("temperature", "valid_max"),
+            Some(255.0)
+        );
+        assert!(dataset
+            .to_cdl()
+            .contains("\t\ttemperature:valid_max = 255UB ;\n"));
+    }
+
+    #[test]
+    fn grid_mapping() {
+        let das = r#"Attributes {
+    temperature {
+        String long_name "2 metre temperature";
+        String grid_mapping "gribfile_projection";
+    }
+    gribfile_projection {
+        String grid_mapping_name "latitude_longitude";
+        Float32 earth_radius 6371229.0;
+    }
+    latitude {
+        String grid_mapping "missing_projection";
+    }
+}"#;
+        let dataset = Dataset::from_bytes(DDS, das).unwrap();
+
+        let mapping = dataset.grid_mapping("temperature").unwrap();
+        let name: String = mapping["grid_mapping_name"]
+            .value
+            .clone()
+            .try_into()
+            .unwrap();
+        assert_eq!(name, "latitude_longitude");
+        assert!(mapping.contains_key("earth_radius"));
+
+        assert!(dataset.grid_mapping("latitude").is_none());
+        assert!(dataset.grid_mapping("time").is_none());
+    }
+
+    #[test]
+    fn fill_values() {
+        let das = r#"Attributes {
+    temperature {
+        Float32 _FillValue 9.96921e36;
+        Float32 missing_value -999.0;
+    }
+    latitude {
+        Int32 missing_value -1, -2;
+    }
+}"#;
+        let dataset = Dataset::from_bytes(DDS, das).unwrap();
+
+        let fills = dataset.fill_values("temperature");
+        assert_eq!(fills.len(), 2);
+        assert!(matches!(fills[0], DataValue::Float32(f) if f == 9.96921e36));
+        assert!(matches!(fills[1], DataValue::Float32(f) if f == -999.0));
+
+        let fills = dataset.fill_values("latitude");
+        assert!(matches!(
+            fills[..],
+            [DataValue::Int32(-1), DataValue::Int32(-2)]
+        ));
+
+        assert!(dataset.fill_values("time").is_empty());
+    }
+
+    #[test]
+    fn nearest_cell() {
+        let lat = (0..81).map(|i| 50.0 - i as f32 * 0.25).collect::<Vec<_>>();
+        let lon = (0..81).map(|i| 280.0 + i as f32 * 0.25).collect::<Vec<_>>();
+
+        let dds = DdsDatasetBuilder::new("gfs.nc")
+            .add_array("lat", DataType::Float32, &[("lat", 81)])
+            .add_grid(
+                "t2m",
+                DataType::Float32,
+                &[("lat", 81), ("x", 81)],
+                &[DataType::Float32, DataType::Float32],
+            )
+            .build()
+            .unwrap();
+        let arrays = HashMap::from([
+            ("t2m".to_string(), DataArray::Float32(vec![0.0; 81 * 81])),
+            ("lat".to_string(), DataArray::Float32(lat)),
+            ("x".to_string(), DataArray::Float32(lon)),
+        ]);
+        let bytes = DodsEncoder::new(dds.clone(), arrays).encode().unwrap();
+        let dods = DodsDataset::from_bytes(&bytes).unwrap();
+
+        let das = r#"Attributes {
+    x {
+        String units "degrees_east";
+    }
+}"#;
+        let dataset = Dataset::new(dds, parse_das_attributes(das).unwrap());
+
+        let cell = dataset.nearest_cell(&dods, "t2m", 41.3, -70.6).unwrap();
+        assert_eq!(cell["lat"], 35);
+        assert_eq!(cell["x"], 38);
+
+        let cell = dataset.nearest_cell(&dods, "lat", 49.9, 0.0);
+        assert!(matches!(cell, Err(Error::InvalidData)));
+        assert!(matches!(
+            dataset.nearest_cell(&dods, "missing", 0.0, 0.0),
+            Err(Error::ParseError)
+        ));
+    }
+
+    #[test]
+    fn display_name() {
+        let das = r#"Attributes {
+    time {
+        String long_name "Epoch Time";
+    }
+}"#;
+        let dataset = Dataset::from_bytes(DDS, das).unwrap();
+
+        assert_eq!(dataset.display_name("time"), "Epoch Time");
+        assert_eq!(dataset.display_name("latitude"), "latitude");
+    }
+
+    #[test]
+    fn full_constraint() {
+        let dataset = Dataset::from_bytes(DDS, "Attributes {\n}").unwrap();
+
+        assert_eq!(
+            dataset.full_constraint("temperature"),
+            Some("temperature[0:3][0:2]".to_string())
+        );
+        assert_eq!(
+            dataset.full_constraint("time_bnds"),
+            Some("time_bnds[0:3][0:1]".to_string())
+        );
+        assert_eq!(dataset.full_constraint("missing"), None);
+    }
+
+    #[test]
+    fn chunk_sizes() {
+        let das = r#"Attributes {
+    temperature {
+        Int32 _ChunkSizes 1, 721, 1440;
+    }
+}"#;
+        let dataset = Dataset::from_bytes(DDS, das).unwrap();
+
+        assert_eq!(dataset.chunk_sizes("temperature"), Some(vec![1, 721, 1440]));
+        assert_eq!(dataset.chunk_sizes("time"), None);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn coveragejson() {
+        use crate::dods::DodsDataset;
+
+        let dds = r#"Dataset {
+    Grid {
+     ARRAY:
+        Float32 sst[lat = 2][lon = 2];
+     MAPS:
+        Float32 lat[lat = 2];
+        Float32 lon[lon = 2];
+    } sst;
+} sst.nc;
+"#;
+        let das = r#"Attributes {
+    sst {
+        String long_name "Sea Surface Temperature";
+        String units "degC";
+        Float32 _FillValue -999.0;
+    }
+}"#;
+
+        let mut dods = format!("{dds}Data:\n").into_bytes();
+        for values in [
+            vec![1.5f32, -999.0, 3.0, 4.5],
+            vec![41.0, 42.0],
+            vec![-71.0, -70.0],
+        ] {
+            dods.extend_from_slice(&(values.len() as u32).to_be_bytes());
+            dods.extend_from_slice(&(values.len() as u32).to_be_bytes());
+            values
+                .iter()
+                .for_each(|v| dods.extend_from_slice(&v.to_be_bytes()));
+        }
+
+        let dataset = Dataset::from_bytes(dds, das).unwrap();
+        let dods = DodsDataset::from_bytes(&dods).unwrap();
+        let coverage = dataset.to_coveragejson(&dods, "sst").unwrap();
+
+        assert_eq!(coverage["type"], "Coverage");
+        assert_eq!(coverage["domain"]["domainType"], "Grid");
+        assert_eq!(coverage["domain"]["axes"]["x"]["values"][1], -70.0);
+        assert_eq!(coverage["domain"]["axes"]["y"]["values"][0], 41.0);
+        assert_eq!(coverage["parameters"]["sst"]["unit"]["symbol"], "degC");
+        assert_eq!(
+            coverage["parameters"]["sst"]["description"]["en"],
+            "Sea Surface Temperature"
+        );
+
+        let range = &coverage["ranges"]["sst"];
+        assert_eq!(range["type"], "NdArray");
+        assert_eq!(range["dataType"], "float");
+        assert_eq!(range["axisNames"], serde_json::json!(["y", "x"]));
+        assert_eq!(range["shape"], serde_json::json!([2, 2]));
+        assert_eq!(range["values"], serde_json::json!([1.5, null, 3.0, 4.5]));
+
+        assert!(dataset.to_coveragejson(&dods, "lat").is_err());
+    }
+}
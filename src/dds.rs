@@ -1,15 +1,19 @@
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_until},
+    bytes::complete::{tag, tag_no_case, take_until},
     character::complete::{multispace0, newline},
-    multi::many_till,
-    sequence::{preceded, terminated},
+    combinator::map,
+    multi::{many0, many_till},
+    sequence::{pair, preceded, terminated},
     IResult,
 };
 
-use crate::{data::DataType, errors::Error};
+use crate::{
+    data::{xdr_padding, DataType},
+    errors::Error,
+};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct DdsArray {
     pub data_type: DataType,
     pub name: String,
@@ -39,7 +43,8 @@ impl DdsArray {
     }
 
     pub fn byte_count(&self) -> usize {
-        8 + self.array_length() as usize * self.data_type.byte_count()
+        let values = self.array_length() as usize * self.data_type.byte_count();
+        8 + values + xdr_padding(values)
     }
 }
 
@@ -57,7 +62,7 @@ fn coordinate(input: &str) -> IResult<&str, (String, u32)> {
     Ok((input, (name.to_string(), len)))
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct DdsGrid {
     pub name: String,
     pub array: DdsArray,
@@ -70,21 +75,22 @@ impl DdsGrid {
         let (input, _) = newline(input)?;
         let (input, _) = multispace0(input)?;
 
-        let (input, _) = tag("ARRAY:")(input)?;
-        let (input, _) = newline(input)?;
-        let (input, _) = multispace0(input)?;
+        // The spec puts the ARRAY section first, but some servers declare the MAPS first
+        let (input, (array, coords)) = alt((
+            pair(
+                Self::parse_array_section,
+                preceded(multispace0, Self::parse_maps_section),
+            ),
+            map(
+                pair(
+                    Self::parse_maps_section,
+                    preceded(multispace0, Self::parse_array_section),
+                ),
+                |(coords, array)| (array, coords),
+            ),
+        ))(input)?;
 
-        let (input, array) = DdsArray::parse(input)?;
-        let (input, _) = newline(input)?;
-        let (input, _) = multispace0(input)?;
-
-        let (input, _) = tag("MAPS:")(input)?;
-        let (input, _) = newline(input)?;
-
-        let (input, (coords, _)) = many_till(
-            preceded(multispace0, terminated(DdsArray::parse, newline)),
-            preceded(multispace0, tag("}")),
-        )(input)?;
+        let (input, _) = preceded(multispace0, tag("}"))(input)?;
 
         let (input, name) = take_until(";")(input)?;
         let (input, _) = tag(";")(input)?;
@@ -100,6 +106,22 @@ impl DdsGrid {
         ))
     }
 
+    fn parse_array_section(input: &str) -> IResult<&str, DdsArray> {
+        let (input, _) = tag_no_case("ARRAY:")(input)?;
+        let (input, _) = newline(input)?;
+        let (input, _) = multispace0(input)?;
+
+        DdsArray::parse(input)
+    }
+
+    fn parse_maps_section(input: &str) -> IResult<&str, Vec<DdsArray>> {
+        let (input, _) = tag_no_case("MAPS:")(input)?;
+        let (input, _) = newline(input)?;
+
+        // The last map may be followed directly by the closing brace without a newline
+        many0(preceded(multispace0, DdsArray::parse))(input)
+    }
+
     pub fn byte_count(&self) -> usize {
         let array_size = self.array.byte_count();
         self.coords
@@ -116,14 +138,14 @@ impl DdsGrid {
             .iter()
             .scan(self.coords_offset(), |acc, c| {
                 let prev = *acc;
-                *acc = *acc + c.byte_count();
+                *acc += c.byte_count();
                 Some(prev)
             })
             .collect()
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum DdsValue {
     Array(DdsArray),
     Grid(DdsGrid),
@@ -158,6 +180,19 @@ impl DdsValue {
         }
     }
 
+    /// The DODS byte count, or `None` when the variable holds strings, whose size depends on
+    /// their contents
+    pub fn fixed_byte_count(&self) -> Option<usize> {
+        let fixed = match self {
+            DdsValue::Array(a) => a.data_type != DataType::String,
+            DdsValue::Grid(g) => {
+                g.array.data_type != DataType::String
+                    && g.coords.iter().all(|c| c.data_type != DataType::String)
+            }
+        };
+        fixed.then(|| self.byte_count())
+    }
+
     pub fn array_data_type(&self) -> DataType {
         match self {
             DdsValue::Array(a) => a.data_type.clone(),
@@ -165,6 +200,13 @@ impl DdsValue {
         }
     }
 
+    pub fn shape(&self) -> Vec<u32> {
+        match self {
+            DdsValue::Array(a) => a.coords.iter().map(|c| c.1).collect(),
+            DdsValue::Grid(g) => g.array.coords.iter().map(|c| c.1).collect(),
+        }
+    }
+
     pub fn coords(&self) -> Vec<String> {
         match self {
             DdsValue::Array(a) => a.coords.iter().map(|c| c.0.clone()).collect(),
@@ -205,20 +247,340 @@ impl DdsDataset {
         let (input, _) = tag("Dataset {")(input)?;
         let (input, _) = newline(input)?;
 
-        let (input, (values, _)) = many_till(DdsValue::parse, tag("}"))(input)?;
+        let (input, (values, _)) =
+            many_till(DdsValue::parse, preceded(multispace0, tag("}")))(input)?;
+
+        // Some servers omit the dataset name entirely, leaving just `}` and `;`
         let (input, name) = take_until(";")(input)?;
         let (input, _) = tag(";")(input)?;
         let name = name.trim().to_string();
 
         Ok((input, DdsDataset { name, values }))
     }
+
+    pub fn variable(&self, key: &str) -> Option<&DdsValue> {
+        self.values.iter().find(|v| v.name() == key)
+    }
+
+    /// The names of the CF coordinate variables: one dimensional arrays named after their dimension
+    pub fn coordinate_variables(&self) -> Vec<String> {
+        self.values
+            .iter()
+            .filter_map(|v| match v {
+                DdsValue::Array(a) if a.coords.len() == 1 && a.coords[0].0 == a.name => {
+                    Some(a.name.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// A hash of the dataset structure (variable names, types, and dimensions) that ignores
+    /// formatting. FNV-1a is used so the value is stable across runs and Rust releases.
+    pub fn schema_hash(&self) -> u64 {
+        let describe = |a: &DdsArray| {
+            let dims = a
+                .coords
+                .iter()
+                .map(|c| format!("{}={}", c.0, c.1))
+                .collect::<Vec<_>>()
+                .join(",");
+            format!("{:?} {}[{dims}]", a.data_type, a.name)
+        };
+
+        let schema = self
+            .values
+            .iter()
+            .map(|v| match v {
+                DdsValue::Array(a) => format!("array {}", describe(a)),
+                DdsValue::Grid(g) => {
+                    let maps = g.coords.iter().map(describe).collect::<Vec<_>>().join(";");
+                    format!("grid {} {} maps {maps}", g.name, describe(&g.array))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        schema.bytes().fold(0xcbf29ce484222325, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    /// Decodes percent-encoded variable, map, and dimension names, so `sea%20surface` becomes
+    /// `sea surface`. Opt in for servers that escape special characters in their DDS.
+    pub fn percent_decode_names(mut self) -> Self {
+        let decode_array = |a: &mut DdsArray| {
+            a.name = percent_decode(&a.name);
+            a.coords.iter_mut().for_each(|c| c.0 = percent_decode(&c.0));
+        };
+
+        self.values.iter_mut().for_each(|v| match v {
+            DdsValue::Array(a) => decode_array(a),
+            DdsValue::Grid(g) => {
+                g.name = percent_decode(&g.name);
+                decode_array(&mut g.array);
+                g.coords.iter_mut().for_each(decode_array);
+            }
+        });
+        self
+    }
+
+    /// Renders the dataset back to DDS text in the layout OpenDAP servers use
+    pub fn to_dds_string(&self) -> String {
+        let declare = |a: &DdsArray, indent: &str| {
+            let dims = a
+                .coords
+                .iter()
+                .map(|c| format!("[{} = {}]", c.0, c.1))
+                .collect::<String>();
+            format!("{indent}{} {}{dims};\n", a.data_type.as_str(), a.name)
+        };
+
+        let mut dds = String::from("Dataset {\n");
+        self.values.iter().for_each(|v| match v {
+            DdsValue::Array(a) => dds.push_str(&declare(a, "    ")),
+            DdsValue::Grid(g) => {
+                dds.push_str("    Grid {\n     ARRAY:\n");
+                dds.push_str(&declare(&g.array, "        "));
+                dds.push_str("     MAPS:\n");
+                g.coords
+                    .iter()
+                    .for_each(|c| dds.push_str(&declare(c, "        ")));
+                dds.push_str(&format!("    }} {};\n", g.name));
+            }
+        });
+        dds.push_str(&format!("}} {};\n", self.name));
+        dds
+    }
+
+    /// Checks that another dataset can be appended to this one along the `along` dimension:
+    /// both must declare the same variables with the same types and dimensions, and every
+    /// dimension other than `along` must have the same size. Fails with `SchemaMismatch` if
+    /// not, or `InvalidData` if `along` is not a dimension of this dataset.
+    pub fn concat_compatible(&self, other: &DdsDataset, along: &str) -> Result<(), Error> {
+        if !self.dimensions_ordered().iter().any(|d| d.0 == along) {
+            return Err(Error::InvalidData);
+        }
+
+        let arrays_compatible = |a: &DdsArray, b: &DdsArray| {
+            a.name == b.name
+                && a.data_type == b.data_type
+                && a.coords.len() == b.coords.len()
+                && a.coords
+                    .iter()
+                    .zip(&b.coords)
+                    .all(|(x, y)| x.0 == y.0 && (x.0 == along || x.1 == y.1))
+        };
+
+        let compatible = self.values.len() == other.values.len()
+            && self
+                .values
+                .iter()
+                .zip(&other.values)
+                .all(|pair| match pair {
+                    (DdsValue::Array(a), DdsValue::Array(b)) => arrays_compatible(a, b),
+                    (DdsValue::Grid(a), DdsValue::Grid(b)) => {
+                        a.name == b.name
+                            && arrays_compatible(&a.array, &b.array)
+                            && a.coords.len() == b.coords.len()
+                            && a.coords
+                                .iter()
+                                .zip(&b.coords)
+                                .all(|(x, y)| arrays_compatible(x, y))
+                    }
+                    _ => false,
+                });
+
+        if compatible {
+            Ok(())
+        } else {
+            Err(Error::SchemaMismatch)
+        }
+    }
+
+    /// Every variable with its full DODS byte count, largest first. String variables have no
+    /// fixed size and come last with `None`.
+    pub fn variables_by_size(&self) -> Vec<(String, Option<usize>)> {
+        let mut sizes = self
+            .values
+            .iter()
+            .map(|v| (v.name(), v.fixed_byte_count()))
+            .collect::<Vec<_>>();
+        sizes.sort_by_key(|s| std::cmp::Reverse(s.1));
+        sizes
+    }
+
+    /// A table of every variable with its type, dimensions, and DODS size in bytes, with the
+    /// columns padded to line up in a terminal. String variables have no fixed size and show
+    /// `-` instead.
+    pub fn summary_table(&self) -> String {
+        let mut rows = vec![[
+            "Variable".to_string(),
+            "Type".to_string(),
+            "Dimensions".to_string(),
+            "Bytes".to_string(),
+        ]];
+        self.values.iter().for_each(|v| {
+            let (kind, coords) = match v {
+                DdsValue::Array(a) => (a.data_type.as_str().to_string(), &a.coords),
+                DdsValue::Grid(g) => (
+                    format!("Grid {}", g.array.data_type.as_str()),
+                    &g.array.coords,
+                ),
+            };
+            let dims = coords
+                .iter()
+                .map(|c| format!("[{} = {}]", c.0, c.1))
+                .collect::<String>();
+            let bytes = v
+                .fixed_byte_count()
+                .map_or("-".to_string(), |b| b.to_string());
+            rows.push([v.name(), kind, dims, bytes]);
+        });
+
+        let widths = (0..4)
+            .map(|i| rows.iter().map(|r| r[i].len()).max().unwrap_or_default())
+            .collect::<Vec<_>>();
+
+        rows.iter()
+            .map(|r| {
+                format!(
+                    "{:<w0$}  {:<w1$}  {:<w2$}  {:>w3$}\n",
+                    r[0],
+                    r[1],
+                    r[2],
+                    r[3],
+                    w0 = widths[0],
+                    w1 = widths[1],
+                    w2 = widths[2],
+                    w3 = widths[3],
+                )
+            })
+            .collect()
+    }
+
+    /// Every dimension with its size, in the order it is first declared
+    pub fn dimensions_ordered(&self) -> Vec<(String, u32)> {
+        let mut dimensions: Vec<(String, u32)> = Vec::new();
+        self.values.iter().for_each(|v| {
+            let coords = match v {
+                DdsValue::Array(a) => &a.coords,
+                DdsValue::Grid(g) => &g.array.coords,
+            };
+            coords.iter().for_each(|c| {
+                if !dimensions.iter().any(|d| d.0 == c.0) {
+                    dimensions.push(c.clone());
+                }
+            });
+        });
+        dimensions
+    }
+}
+
+/// Replaces each `%XX` escape with the byte it encodes. Names that would not decode to valid
+/// UTF-8 are left as they are.
+fn percent_decode(name: &str) -> String {
+    let bytes = name.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).unwrap_or_else(|_| name.to_string())
+}
+
+/// Builds a `DdsDataset` in code rather than parsing DDS text, for synthetic datasets and fixtures
+#[derive(Clone, Debug)]
+pub struct DdsDatasetBuilder {
+    name: String,
+    values: Vec<DdsValue>,
+    mismatched_maps: bool,
+}
+
+impl DdsDatasetBuilder {
+    pub fn new(name: &str) -> Self {
+        DdsDatasetBuilder {
+            name: name.to_string(),
+            values: Vec::new(),
+            mismatched_maps: false,
+        }
+    }
+
+    pub fn add_array(mut self, name: &str, data_type: DataType, dims: &[(&str, u32)]) -> Self {
+        self.values
+            .push(DdsValue::Array(builder_array(name, data_type, dims)));
+        self
+    }
+
+    /// Adds a grid whose maps are one dimensional arrays named after each dimension, with
+    /// `map_types` giving the type of each map in dimension order
+    pub fn add_grid(
+        mut self,
+        name: &str,
+        data_type: DataType,
+        dims: &[(&str, u32)],
+        map_types: &[DataType],
+    ) -> Self {
+        self.mismatched_maps |= map_types.len() != dims.len();
+        let coords = dims
+            .iter()
+            .zip(map_types.iter())
+            .map(|(dim, map_type)| builder_array(dim.0, map_type.clone(), &[*dim]))
+            .collect();
+
+        self.values.push(DdsValue::Grid(DdsGrid {
+            name: name.to_string(),
+            array: builder_array(name, data_type, dims),
+            coords,
+        }));
+        self
+    }
+
+    /// Fails with `InvalidData` if a grid was given a different number of map types than dimensions
+    pub fn build(self) -> Result<DdsDataset, Error> {
+        if self.mismatched_maps {
+            return Err(Error::InvalidData);
+        }
+
+        Ok(DdsDataset {
+            name: self.name,
+            values: self.values,
+        })
+    }
+}
+
+fn builder_array(name: &str, data_type: DataType, dims: &[(&str, u32)]) -> DdsArray {
+    DdsArray {
+        data_type,
+        name: name.to_string(),
+        coords: dims.iter().map(|d| (d.0.to_string(), d.1)).collect(),
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::dds::{DataType, DdsValue};
+    use crate::{
+        dds::{DataType, DdsValue},
+        errors::Error,
+    };
 
-    use super::{coordinate, DdsArray, DdsDataset, DdsGrid};
+    use super::{coordinate, DdsArray, DdsDataset, DdsDatasetBuilder, DdsGrid};
 
     #[test]
     fn parse_coords() {
@@ -297,6 +659,63 @@ mod tests {
     }
 
     #[test]
+    fn parse_grid_lowercase_labels() {
+        let grid_input = r#"Grid {
+     array:
+        Int32 mean_wave_dir[time = 7][frequency = 64];
+     maps:
+        Int32 time[time = 7];
+        Float32 frequency[frequency = 64];
+    } mean_wave_dir;"#;
+
+        let (_, grid) = DdsGrid::parse(grid_input).unwrap();
+
+        assert_eq!(grid.name, "mean_wave_dir");
+        assert_eq!(grid.array.data_type, DataType::Int32);
+        assert_eq!(grid.coords.len(), 2);
+        assert_eq!(grid.coords[0].name, "time");
+        assert_eq!(grid.coords[1].name, "frequency");
+    }
+
+    #[test]
+    fn parse_grid_without_trailing_newline() {
+        let grid_input = r#"Grid {
+     ARRAY:
+        Float32 wind_spd[time = 3][latitude = 1];
+     MAPS:
+        Int32 time[time = 3];
+        Float32 latitude[latitude = 1];} wind_spd;"#;
+
+        let (_, grid) = DdsGrid::parse(grid_input).unwrap();
+
+        assert_eq!(grid.name, "wind_spd");
+        assert_eq!(grid.coords.len(), 2);
+        assert_eq!(grid.coords[1].name, "latitude");
+    }
+
+    #[test]
+    fn parse_grid_maps_first() {
+        let grid_input = r#"Grid {
+     MAPS:
+        Int32 time[time = 7];
+        Float32 frequency[frequency = 64];
+     ARRAY:
+        Float32 wave_spectrum_r1[time = 7][frequency = 64];
+    } wave_spectrum_r1;"#;
+
+        let (_, grid) = DdsGrid::parse(grid_input).unwrap();
+
+        assert_eq!(grid.name, "wave_spectrum_r1");
+        assert_eq!(grid.array.name, "wave_spectrum_r1");
+        assert_eq!(grid.array.array_length(), 7 * 64);
+        assert_eq!(grid.coords.len(), 2);
+        assert_eq!(grid.coords[0].name, "time");
+        assert_eq!(grid.coords[1].name, "frequency");
+        assert_eq!(grid.coords_offset(), grid.array.byte_count());
+    }
+
+    #[test]
+    #[allow(clippy::match_like_matches_macro)]
     fn parse_dds() {
         let dataset_input = r#"Dataset {
     Int32 time[time = 7];
@@ -333,4 +752,282 @@ mod tests {
             false
         });
     }
+
+    #[test]
+    fn dimensions_ordered() {
+        let dataset_input = r#"Dataset {
+    Int32 time[time = 7];
+    Float32 frequency[frequency = 64];
+    Grid {
+     ARRAY:
+        Float32 spectral_wave_density[time = 7][frequency = 64][latitude = 1][longitude = 1];
+     MAPS:
+        Int32 time[time = 7];
+        Float32 frequency[frequency = 64];
+        Float32 latitude[latitude = 1];
+        Float32 longitude[longitude = 1];
+    } spectral_wave_density;
+} data/swden/44097/44097w9999.nc;
+"#;
+
+        let (_, dataset) = DdsDataset::parse(dataset_input).unwrap();
+
+        let dimensions = dataset.dimensions_ordered();
+        assert_eq!(
+            dimensions,
+            vec![
+                ("time".to_string(), 7),
+                ("frequency".to_string(), 64),
+                ("latitude".to_string(), 1),
+                ("longitude".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn schema_hash() {
+        let compact = r#"Dataset {
+Int32 time[time=7];
+Grid {
+ARRAY:
+Float32 gust[time=7][latitude=1];
+MAPS:
+Int32 time[time=7];
+Float32 latitude[latitude=1];} gust;
+} a;"#;
+        let spaced = r#"Dataset {
+    Int32 time[time = 7];
+    Grid {
+     ARRAY:
+        Float32 gust[time = 7][latitude = 1];
+     MAPS:
+        Int32 time[time = 7];
+        Float32 latitude[latitude = 1];
+    } gust;
+} b;"#;
+        let resized = spaced.replace("7", "8");
+
+        let compact = DdsDataset::from_bytes(compact).unwrap();
+        let spaced = DdsDataset::from_bytes(spaced).unwrap();
+        let resized = DdsDataset::from_bytes(&resized).unwrap();
+
+        assert_eq!(compact.schema_hash(), spaced.schema_hash());
+        assert_ne!(spaced.schema_hash(), resized.schema_hash());
+    }
+
+    #[test]
+    fn coordinate_variables() {
+        let input = r#"Dataset {
+    Float32 lat[lat = 3];
+    Float32 lon[lon = 4];
+    Int32 time[time = 2];
+    Float32 time_bnds[time = 2][nv = 2];
+    Float32 station_depth[station = 5];
+    Grid {
+     ARRAY:
+        Float32 temperature[time = 2][lat = 3][lon = 4];
+     MAPS:
+        Int32 time[time = 2];
+        Float32 lat[lat = 3];
+        Float32 lon[lon = 4];
+    } temperature;
+} sample.nc;"#;
+        let dataset = DdsDataset::from_bytes(input).unwrap();
+
+        assert_eq!(dataset.coordinate_variables(), vec!["lat", "lon", "time"]);
+    }
+
+    #[test]
+    fn parse_dds_without_name() {
+        let input = "Dataset {\n    Int32 time[time = 7];\n}\n;";
+        let dataset = DdsDataset::from_bytes(input).unwrap();
+        assert_eq!(dataset.name, "");
+        assert_eq!(dataset.values.len(), 1);
+
+        let input = "Dataset {\n    Int32 time[time = 7];\n    };";
+        let dataset = DdsDataset::from_bytes(input).unwrap();
+        assert_eq!(dataset.name, "");
+        assert_eq!(dataset.values.len(), 1);
+    }
+
+    #[test]
+    fn variables_by_size() {
+        let input = r#"Dataset {
+    Int32 time[time = 4];
+    Float32 latitude[latitude = 10];
+    Grid {
+     ARRAY:
+        Float32 temperature[time = 4][latitude = 10];
+     MAPS:
+        Int32 time[time = 4];
+        Float32 latitude[latitude = 10];
+    } temperature;
+    String station[station = 2];
+    Float32 wind_speed[time = 4][latitude = 10];
+} weather.nc;"#;
+        let dataset = DdsDataset::from_bytes(input).unwrap();
+
+        let sizes = dataset.variables_by_size();
+        let names = sizes.iter().map(|s| s.0.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["temperature", "wind_speed", "latitude", "time", "station"]
+        );
+        assert_eq!(sizes[0].1, Some(8 + 40 * 4 + 8 + 4 * 4 + 8 + 10 * 4));
+        assert_eq!(sizes[1].1, Some(8 + 40 * 4));
+        assert_eq!(sizes[3].1, Some(8 + 4 * 4));
+        assert_eq!(sizes[4].1, None);
+    }
+
+    #[test]
+    fn build_dataset() {
+        let dataset = DdsDatasetBuilder::new("built.nc")
+            .add_array("time", DataType::Int32, &[("time", 3)])
+            .add_grid(
+                "temperature",
+                DataType::Float32,
+                &[("time", 3), ("latitude", 2)],
+                &[DataType::Int32, DataType::Float32],
+            )
+            .build()
+            .unwrap();
+
+        assert_eq!(dataset.values.len(), 2);
+        let grid = dataset.variable("temperature").unwrap().grid().unwrap();
+        assert_eq!(grid.coords[1].name, "latitude");
+        assert_eq!(grid.coords[1].coords, vec![("latitude".to_string(), 2)]);
+
+        let text = dataset.to_dds_string();
+        let reparsed = DdsDataset::from_bytes(&text).unwrap();
+        assert_eq!(reparsed.name, "built.nc");
+        assert_eq!(reparsed.values, dataset.values);
+
+        assert!(DdsDatasetBuilder::new("bad.nc")
+            .add_grid("x", DataType::Float32, &[("x", 1)], &[])
+            .build()
+            .is_err());
+        assert!(DdsDatasetBuilder::new("bad.nc")
+            .add_grid(
+                "x",
+                DataType::Int32,
+                &[("x", 1)],
+                &[DataType::Int32, DataType::Float32]
+            )
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn concat_compatible() {
+        let cycle = |times: u32, lats: u32, data_type: DataType| {
+            DdsDatasetBuilder::new("gfs.nc")
+                .add_array("time", DataType::Int32, &[("time", times)])
+                .add_grid(
+                    "t2m",
+                    data_type,
+                    &[("time", times), ("lat", lats)],
+                    &[DataType::Int32, DataType::Float32],
+                )
+                .build()
+                .unwrap()
+        };
+
+        let first = cycle(4, 10, DataType::Float32);
+        assert!(first
+            .concat_compatible(&cycle(8, 10, DataType::Float32), "time")
+            .is_ok());
+
+        assert!(matches!(
+            first.concat_compatible(&cycle(4, 12, DataType::Float32), "time"),
+            Err(Error::SchemaMismatch)
+        ));
+        assert!(matches!(
+            first.concat_compatible(&cycle(4, 10, DataType::Int32), "time"),
+            Err(Error::SchemaMismatch)
+        ));
+        assert!(matches!(
+            first.concat_compatible(&cycle(4, 12, DataType::Float32), "level"),
+            Err(Error::InvalidData)
+        ));
+
+        let extra = DdsDatasetBuilder::new("gfs.nc")
+            .add_array("time", DataType::Int32, &[("time", 4)])
+            .build()
+            .unwrap();
+        assert!(matches!(
+            first.concat_compatible(&extra, "time"),
+            Err(Error::SchemaMismatch)
+        ));
+    }
+
+    #[test]
+    fn percent_decode_names() {
+        let input = r#"Dataset {
+    Float32 sea%20surface%5Ftemp[time%20step = 2];
+    Grid {
+     ARRAY:
+        Float32 wind%2Fspeed[time%20step = 2];
+     MAPS:
+        Int32 time%20step[time%20step = 2];
+    } wind%2Fspeed;
+    Int32 growth%[n = 1];
+} encoded.nc;"#;
+        let dataset = DdsDataset::from_bytes(input).unwrap();
+        assert!(dataset.variable("sea%20surface%5Ftemp").is_some());
+
+        let dataset = dataset.percent_decode_names();
+        let array = dataset
+            .variable("sea surface_temp")
+            .unwrap()
+            .array()
+            .unwrap();
+        assert_eq!(array.coords[0].0, "time step");
+
+        let grid = dataset.variable("wind/speed").unwrap().grid().unwrap();
+        assert_eq!(grid.array.name, "wind/speed");
+        assert_eq!(grid.coords[0].name, "time step");
+
+        assert!(dataset.variable("growth%").is_some());
+    }
+
+    #[test]
+    fn summary_table() {
+        let dataset = DdsDatasetBuilder::new("summary.nc")
+            .add_array("time", DataType::Int32, &[("time", 7)])
+            .add_grid(
+                "spectral_wave_density",
+                DataType::Float32,
+                &[("time", 7), ("frequency", 64)],
+                &[DataType::Int32, DataType::Float32],
+            )
+            .add_array("station", DataType::String, &[("station", 1)])
+            .build()
+            .unwrap();
+
+        let table = dataset.summary_table();
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("Variable"));
+        assert!(lines[1].starts_with("time "));
+        assert!(lines[1].contains("[time = 7]"));
+        assert!(lines[2].starts_with("spectral_wave_density  Grid Float32"));
+        assert!(lines[2].contains("[time = 7][frequency = 64]"));
+        assert!(lines[2].ends_with(&dataset.values[1].byte_count().to_string()));
+        assert!(lines[3].starts_with("station "));
+        assert!(lines[3].contains("String"));
+        assert!(lines[3].ends_with(" -"));
+
+        // Every row is padded to the same width so the byte counts line up
+        assert!(lines.iter().all(|l| l.len() == lines[0].len()));
+    }
+
+    #[test]
+    fn parse_64_bit_array() {
+        let (_, array) = DdsArray::parse("UInt64 counts[time = 3];").unwrap();
+        assert_eq!(array.data_type, DataType::UInt64);
+        assert_eq!(array.byte_count(), 8 + 3 * 8);
+
+        let (_, array) = DdsArray::parse("Int64 time[time = 3];").unwrap();
+        assert_eq!(array.data_type, DataType::Int64);
+    }
 }
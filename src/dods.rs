@@ -1,29 +1,123 @@
+use std::collections::HashMap;
+
 use crate::{
-    data::{DataArray, DataValueIterator},
-    dds::DdsDataset,
+    data::{xdr_padding, ByteOrder, DataArray, DataType, DataValue, DataValueIterator},
+    dds::{DdsArray, DdsDataset},
     errors::Error,
+    indexing::strides,
     DdsValue,
 };
 
+/// The DDS text at the start of a DODS response, up to the `Data:` marker
+pub fn extract_dds_text(bytes: &[u8]) -> Option<&str> {
+    let (marker, _) = find_data_marker(bytes)?;
+    std::str::from_utf8(&bytes[..marker]).ok()
+}
+
+/// The position and length of the marker separating the DDS from the binary data. Some
+/// servers send `DATA:` or `Data :`, so the casing and a space before the colon are tolerated.
+fn find_data_marker(bytes: &[u8]) -> Option<(usize, usize)> {
+    (0..bytes.len()).find_map(|i| {
+        let word = bytes.get(i..i + 4)?;
+        if !word.eq_ignore_ascii_case(b"data") {
+            return None;
+        }
+
+        let rest = &bytes[i + 4..];
+        let space = usize::from(rest.first() == Some(&b' '));
+        rest[space..]
+            .starts_with(b":\n")
+            .then_some((i, 4 + space + 2))
+    })
+}
+
+#[derive(Clone, Debug)]
+pub struct DodsVariable {
+    pub index: usize,
+    pub offset: usize,
+    pub data_type: DataType,
+    pub shape: Vec<u32>,
+}
+
 #[derive(Clone, Debug)]
 pub struct DodsDataset<'a> {
+    /// The DDS the variable layout was computed from when parsing. Changing it afterwards
+    /// does not update the layout used to locate variables in `data_bytes`.
     pub dds: DdsDataset,
     pub data_bytes: &'a[u8],
+    layout: HashMap<String, DodsVariable>,
 }
 
 impl <'a> DodsDataset<'a> {
     pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
-        let dods_string = String::from_utf8_lossy(&bytes);
-        let (_, dds) = DdsDataset::parse(&dods_string).map_err(|_| Error::ParseError)?;
-
-        let binary_data_start = match dods_string.find("Data:\n") {
-            Some(p) => Ok(p),
+        let (marker, marker_len) = match find_data_marker(bytes) {
+            Some(m) => Ok(m),
             None => Err(Error::InvalidData),
-        }? + 6;
+        }?;
+        let dds_text = String::from_utf8_lossy(&bytes[..marker]);
+        let (_, dds) = DdsDataset::parse(&dds_text).map_err(|_| Error::ParseError)?;
 
+        let binary_data_start = marker + marker_len;
         let data_bytes = &bytes[binary_data_start..];
 
-        Ok(DodsDataset { dds, data_bytes })
+        let layout = dds
+            .values
+            .iter()
+            .enumerate()
+            // Nothing can be located past a variable whose size is unknown, so stop there
+            .scan(0, |offset, (index, v)| {
+                let variable = DodsVariable {
+                    index,
+                    offset: *offset,
+                    data_type: v.array_data_type(),
+                    shape: v.shape(),
+                };
+                *offset += v.fixed_byte_count()?;
+                Some((v.name(), variable))
+            })
+            .collect();
+
+        Ok(DodsDataset {
+            dds,
+            data_bytes,
+            layout,
+        })
+    }
+
+    /// Parses a DODS response that may carry extra bytes after the expected data section,
+    /// returning the dataset along with the number of trailing bytes that were ignored
+    pub fn from_bytes_lenient(bytes: &'a [u8]) -> Result<(Self, usize), Error> {
+        let mut dataset = Self::from_bytes(bytes)?;
+
+        let expected = dataset
+            .dds
+            .values
+            .iter()
+            .map(DdsValue::fixed_byte_count)
+            .sum::<Option<usize>>()
+            .ok_or(Error::NotImplemented)?;
+        if dataset.data_bytes.len() < expected {
+            return Err(Error::InvalidData);
+        }
+
+        let ignored = dataset.data_bytes.len() - expected;
+        dataset.data_bytes = &dataset.data_bytes[..expected];
+
+        Ok((dataset, ignored))
+    }
+
+    /// Checks that the DDS embedded in this response describes the same variables, types,
+    /// and shapes as a previously fetched DDS
+    pub fn verify_against(&self, dds: &DdsDataset) -> Result<(), Error> {
+        if self.dds.values == dds.values {
+            Ok(())
+        } else {
+            Err(Error::SchemaMismatch)
+        }
+    }
+
+    pub fn variable(&self, key: &str) -> Option<&DodsVariable> {
+        self.layout.get(key)
     }
 
     pub fn variables(&self) -> Vec<String> {
@@ -31,16 +125,27 @@ impl <'a> DodsDataset<'a> {
     }
 
     pub fn variable_index(&self, key: &str) -> Option<usize> {
-        self.dds.values.iter().position(|v| v.name() == key)
+        self.variable(key).map(|v| v.index)
     }
 
     pub fn variable_byte_offset(&self, key: &str) -> Option<usize> {
-        let position = self.variable_index(key)?;
-        let offset = (0usize..position).fold(0, |acc, i| acc + self.dds.values[i].byte_count());
-        Some(offset)
+        self.variable(key).map(|v| v.offset)
     }
 
-    pub fn variable_data_iter(&self, key: &str) -> Result<DataValueIterator, Error> {
+    pub fn variable_byte_ranges(&self) -> Vec<(String, usize, usize)> {
+        self.dds
+            .values
+            .iter()
+            .scan(0, |offset, v| {
+                let start = *offset;
+                *offset += v.fixed_byte_count()?;
+                Some((v.name(), start, *offset))
+            })
+            .collect()
+    }
+
+    /// The CRC32 of a variable's raw bytes in the response, including the maps of a grid
+    pub fn variable_checksum(&self, key: &str) -> Result<u32, Error> {
         let index = match self.variable_index(key) {
             Some(o) => Ok(o),
             None => Err(Error::ParseError),
@@ -51,19 +156,78 @@ impl <'a> DodsDataset<'a> {
             None => Err(Error::ParseError),
         }?;
 
-        match &self.dds.values[index] {
-            DdsValue::Array(a) => DataValueIterator::new(
-                &self.data_bytes[offset..offset + a.byte_count() as usize ],
-                a.data_type.clone(),
-            ),
-            DdsValue::Grid(g) => DataValueIterator::new(
-                &self.data_bytes[offset..offset + g.array.byte_count() as usize],
-                g.array.data_type.clone(),
-            ),
+        let end = offset + self.dds.values[index].byte_count();
+        match self.data_bytes.get(offset..end) {
+            Some(bytes) => Ok(crc32fast::hash(bytes)),
+            None => Err(Error::InvalidData),
         }
     }
 
-    pub fn variable_data(&self, key: &str) -> Result<DataArray, Error> {
+    /// The two count words that precede a variable's values, read without decoding the data.
+    /// For a grid these belong to the data array. They should always be equal.
+    pub fn variable_count_headers(&self, key: &str) -> Result<(u32, u32), Error> {
+        let offset = match self.variable_byte_offset(key) {
+            Some(o) => Ok(o),
+            None => Err(Error::ParseError),
+        }?;
+
+        let header = self
+            .data_bytes
+            .get(offset..offset + 8)
+            .ok_or(Error::InvalidData)?;
+        let count = |word: &[u8]| u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+
+        Ok((count(&header[..4]), count(&header[4..])))
+    }
+
+    /// The big endian values of a variable as they sit in the response, after the count
+    /// header. For a grid this is only the data array, not its maps.
+    pub fn variable_raw_bytes(&self, key: &str) -> Result<&'a [u8], Error> {
+        let variable = self.variable(key).ok_or(Error::ParseError)?;
+        let length = variable.shape.iter().product::<u32>() as usize;
+
+        let start = variable.offset + 8;
+        self.data_bytes
+            .get(start..start + length * variable.data_type.byte_count())
+            .ok_or(Error::InvalidData)
+    }
+
+    /// Reads the single element at the given indices of a variable straight from the
+    /// response bytes, without decoding the rest of the array
+    pub fn value_at(&self, key: &str, indices: &[usize]) -> Result<DataValue, Error> {
+        let variable = self.variable(key).ok_or(Error::ParseError)?;
+        let shape = variable
+            .shape
+            .iter()
+            .map(|s| *s as usize)
+            .collect::<Vec<_>>();
+        if indices.len() != shape.len() || indices.iter().zip(&shape).any(|(i, s)| i >= s) {
+            return Err(Error::InvalidData);
+        }
+
+        let element = indices
+            .iter()
+            .zip(strides(&shape))
+            .map(|(i, stride)| i * stride)
+            .sum::<usize>();
+        if variable.data_type == DataType::String {
+            return Err(Error::NotImplemented);
+        }
+
+        let size = variable.data_type.byte_count();
+        let start = variable.offset + 8 + element * size;
+        let bytes = self
+            .data_bytes
+            .get(start..start + size)
+            .ok_or(Error::InvalidData)?;
+
+        let (_, value) =
+            DataArray::parse_values(bytes, variable.data_type.clone(), 1, ByteOrder::BigEndian)
+                .map_err(|_| Error::ParseError)?;
+        value.get(0).ok_or(Error::InvalidData)
+    }
+
+    pub fn variable_data_iter(&self, key: &str) -> Result<DataValueIterator<'_>, Error> {
         let index = match self.variable_index(key) {
             Some(o) => Ok(o),
             None => Err(Error::ParseError),
@@ -74,15 +238,37 @@ impl <'a> DodsDataset<'a> {
             None => Err(Error::ParseError),
         }?;
 
-        let (_, data) = match &self.dds.values[index] {
-            DdsValue::Array(a) => DataArray::parse(&self.data_bytes[offset..], a.data_type.clone()),
-            DdsValue::Grid(g) => {
-                DataArray::parse(&self.data_bytes[offset..], g.array.data_type.clone())
-            }
-        }
-        .map_err(|_| Error::ParseError)?;
+        let array = match &self.dds.values[index] {
+            DdsValue::Array(a) => a,
+            DdsValue::Grid(g) => &g.array,
+        };
+        let bytes = self
+            .data_bytes
+            .get(offset..offset + array.byte_count())
+            .ok_or(Error::InvalidData)?;
+        DataValueIterator::new(bytes, array.data_type.clone())
+    }
+
+    pub fn variable_data(&self, key: &str) -> Result<DataArray, Error> {
+        let index = match self.variable_index(key) {
+            Some(o) => Ok(o),
+            None => Err(Error::ParseError),
+        }?;
 
-        Ok(data)
+        let offset = match self.variable_byte_offset(key) {
+            Some(o) => Ok(o),
+            None => Err(Error::ParseError),
+        }?;
+
+        let array = match &self.dds.values[index] {
+            DdsValue::Array(a) => a,
+            DdsValue::Grid(g) => &g.array,
+        };
+        let bytes = self
+            .data_bytes
+            .get(offset..offset + array.byte_count())
+            .ok_or(Error::InvalidData)?;
+        DataArray::from_bytes(bytes, array.data_type.clone())
     }
 
     pub fn variable_coords(&self, key: &str) -> Result<Vec<(String, DataArray)>, Error> {
@@ -99,23 +285,173 @@ impl <'a> DodsDataset<'a> {
         match &self.dds.values[index] {
             DdsValue::Array(a) => {
                 let name = a.name.clone();
-                DataArray::parse(&self.data_bytes[position..], a.data_type.clone())
-                    .map_err(|_| Error::ParseError)
-                    .map(|(_, a)| vec![(name, a)])
+                let bytes = self
+                    .data_bytes
+                    .get(position..position + a.byte_count())
+                    .ok_or(Error::InvalidData)?;
+                DataArray::from_bytes(bytes, a.data_type.clone()).map(|a| vec![(name, a)])
             }
             DdsValue::Grid(g) => g
                 .coords
                 .iter()
                 .scan(g.coords_offset(), |acc, c| {
                     let name = c.name.clone();
-                    let data =
-                        DataArray::parse(&self.data_bytes[position + *acc..], c.data_type.clone())
-                            .map_err(|_| Error::ParseError)
-                            .map(|(_, a)| (name, a));
-                    *acc = *acc + c.byte_count();
+                    let start = position + *acc;
+                    let data = self
+                        .data_bytes
+                        .get(start..start + c.byte_count())
+                        .ok_or(Error::InvalidData)
+                        .and_then(|bytes| DataArray::from_bytes(bytes, c.data_type.clone()))
+                        .map(|a| (name, a));
+                    *acc += c.byte_count();
                     Some(data)
                 })
                 .collect(),
         }
     }
+
+    /// Flattens a variable into tidy records, one per element in row-major order, each holding
+    /// the value under the variable name and the matching value of every grid map
+    pub fn tidy_records(
+        &self,
+        key: &str,
+    ) -> Result<impl Iterator<Item = HashMap<String, DataValue>> + '_, Error> {
+        let variable = self.variable(key).ok_or(Error::ParseError)?;
+        let shape = variable
+            .shape
+            .iter()
+            .map(|s| *s as usize)
+            .collect::<Vec<_>>();
+        let coords = match &self.dds.values[variable.index] {
+            DdsValue::Array(_) => Vec::new(),
+            DdsValue::Grid(_) => self.variable_coords(key)?,
+        };
+        let strides = strides(&shape);
+        let name = key.to_string();
+
+        Ok(self
+            .variable_data_iter(key)?
+            .enumerate()
+            .map(move |(element, value)| {
+                let mut record = coords
+                    .iter()
+                    .zip(strides.iter().zip(&shape))
+                    .filter_map(|((coord, data), (stride, size))| {
+                        data.get(element / stride % size)
+                            .map(|v| (coord.clone(), v))
+                    })
+                    .collect::<HashMap<_, _>>();
+                record.insert(name.clone(), value);
+                record
+            }))
+    }
+
+    /// Builds a GeoJSON FeatureCollection of points from parallel one dimensional station or
+    /// trajectory arrays, one feature per element with the value as a property. Sequences are
+    /// not parsed yet, so the fields must be flat arrays of the same length.
+    #[cfg(feature = "json")]
+    pub fn to_geojson(
+        &self,
+        lat_field: &str,
+        lon_field: &str,
+        value_field: &str,
+    ) -> Result<serde_json::Value, Error> {
+        use serde_json::json;
+
+        let lats = self.variable_data(lat_field)?.to_json_values();
+        let lons = self.variable_data(lon_field)?.to_json_values();
+        let values = self.variable_data(value_field)?.to_json_values();
+
+        if lats.len() != lons.len() || lats.len() != values.len() {
+            return Err(Error::CountMismatch);
+        }
+
+        let features = lats
+            .into_iter()
+            .zip(lons)
+            .zip(values)
+            .map(|((lat, lon), value)| {
+                json!({
+                    "type": "Feature",
+                    "geometry": { "type": "Point", "coordinates": [lon, lat] },
+                    "properties": { value_field: value },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(json!({
+            "type": "FeatureCollection",
+            "features": features,
+        }))
+    }
+}
+
+/// Serializes arrays into a DODS response, the inverse of `DodsDataset::from_bytes`. Grid
+/// data is looked up by the grid name and each map by its own name.
+#[derive(Clone, Debug)]
+pub struct DodsEncoder {
+    pub dds: DdsDataset,
+    pub arrays: HashMap<String, DataArray>,
+}
+
+impl DodsEncoder {
+    pub fn new(dds: DdsDataset, arrays: HashMap<String, DataArray>) -> Self {
+        DodsEncoder { dds, arrays }
+    }
+
+    /// The DDS text, the `Data:` marker, and every variable as big endian values behind the
+    /// repeated count header
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        let mut bytes = self.dds.to_dds_string().into_bytes();
+        bytes.extend_from_slice(b"Data:\n");
+
+        for value in &self.dds.values {
+            match value {
+                DdsValue::Array(a) => self.encode_array(&mut bytes, a)?,
+                DdsValue::Grid(g) => {
+                    self.encode_array(&mut bytes, &g.array)?;
+                    for c in &g.coords {
+                        self.encode_array(&mut bytes, c)?;
+                    }
+                }
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    fn encode_array(&self, bytes: &mut Vec<u8>, array: &DdsArray) -> Result<(), Error> {
+        let data = self.arrays.get(&array.name).ok_or(Error::InvalidData)?;
+        if data.data_type() != array.data_type {
+            return Err(Error::SchemaMismatch);
+        }
+
+        let length = data.len();
+        if length != array.array_length() as usize {
+            return Err(Error::CountMismatch);
+        }
+
+        bytes.extend_from_slice(&(length as u32).to_be_bytes());
+        bytes.extend_from_slice(&(length as u32).to_be_bytes());
+        match data {
+            DataArray::Byte(v) => {
+                v.iter().for_each(|b| bytes.push(*b as u8));
+                bytes.resize(bytes.len() + xdr_padding(length), 0);
+            }
+            DataArray::Int32(v) => v
+                .iter()
+                .for_each(|i| bytes.extend_from_slice(&i.to_be_bytes())),
+            DataArray::Int64(v) => v
+                .iter()
+                .for_each(|i| bytes.extend_from_slice(&i.to_be_bytes())),
+            DataArray::UInt64(v) => v
+                .iter()
+                .for_each(|i| bytes.extend_from_slice(&i.to_be_bytes())),
+            DataArray::Float32(v) => v
+                .iter()
+                .for_each(|f| bytes.extend_from_slice(&f.to_be_bytes())),
+        }
+
+        Ok(())
+    }
 }
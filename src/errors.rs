@@ -10,4 +10,10 @@ pub enum Error {
 	InvalidTypecast,
     #[error("Not Implemented")]
 	NotImplemented,
+    #[error("Count Mismatch")]
+	CountMismatch,
+    #[error("Schema Mismatch")]
+	SchemaMismatch,
+    #[error("Checksum Mismatch: {variable}")]
+	ChecksumMismatch { variable: String },
 }
\ No newline at end of file
@@ -0,0 +1,21 @@
+/// The number of elements to step over for each dimension of a row-major array
+pub fn strides(shape: &[usize]) -> Vec<usize> {
+    let mut strides = vec![1; shape.len()];
+    for i in (0..shape.len().saturating_sub(1)).rev() {
+        strides[i] = strides[i + 1] * shape[i + 1];
+    }
+    strides
+}
+
+#[cfg(test)]
+mod tests {
+    use super::strides;
+
+    #[test]
+    fn row_major_strides() {
+        assert_eq!(strides(&[7, 64, 1, 1]), vec![64, 1, 1, 1]);
+        assert_eq!(strides(&[4, 3, 2]), vec![6, 2, 1]);
+        assert_eq!(strides(&[5]), vec![1]);
+        assert!(strides(&[]).is_empty());
+    }
+}
@@ -1,9 +1,13 @@
+pub mod dap4;
 pub mod das;
 pub mod data;
+pub mod dataset;
 pub mod dds;
 pub mod dods;
 pub mod errors;
+pub mod indexing;
 
 pub use das::*;
+pub use dataset::*;
 pub use dds::*;
 pub use dods::*;
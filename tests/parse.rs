@@ -1,6 +1,12 @@
-use std::fs;
+use std::{collections::HashMap, fs};
 
-use readap::{data::DataArray, parse_das_attributes, DdsDataset, DodsDataset};
+use readap::{
+    dap4::Dap4Response,
+    data::{DataArray, DataType, DataValue},
+    dods::{extract_dds_text, DodsEncoder},
+    errors::Error,
+    parse_das_attributes, Dataset, DdsDataset, DdsDatasetBuilder, DodsDataset,
+};
 
 #[test]
 fn read_das() {
@@ -18,6 +24,7 @@ fn read_das() {
 }
 
 #[test]
+#[allow(clippy::needless_borrow)]
 fn read_dds() {
     let input = &fs::read_to_string("./data/44008.ncml.dds").unwrap();
 
@@ -27,6 +34,20 @@ fn read_dds() {
 }
 
 #[test]
+fn dds_string_round_trip() {
+    let input = &fs::read_to_string("./data/44008.ncml.dds").unwrap();
+    let dataset = DdsDataset::from_bytes(input).unwrap();
+
+    let reparsed = DdsDataset::from_bytes(&dataset.to_dds_string()).unwrap();
+    assert_eq!(reparsed.values, dataset.values);
+
+    let input = &fs::read_to_string("./data/swden/44097w9999.nc.dds").unwrap();
+    let dataset = DdsDataset::from_bytes(input).unwrap();
+    assert_eq!(dataset.to_dds_string().trim_end(), input.trim_end());
+}
+
+#[test]
+#[allow(clippy::needless_borrow)]
 fn read_dataset() {
     let input = &fs::read("./data/swden/44097w9999.nc.dods").unwrap();
 
@@ -78,3 +99,518 @@ fn read_dataset() {
 
     assert_eq!(mwd_unpacked.len(), mwd.len());
 }
+
+#[test]
+fn read_dap4() {
+    let input = &fs::read("./data/dap4/sample.dap").unwrap();
+
+    let response = Dap4Response::from_bytes(input).unwrap();
+    assert_eq!(response.dds.name, "sample.nc");
+    assert_eq!(response.variables(), vec!["time", "temperature"]);
+
+    let time: Vec<i32> = response.variable_data("time").unwrap().try_into().unwrap();
+    assert_eq!(time, vec![1700000000, 1700003600, 1700007200]);
+
+    let temperature: Vec<f32> = response
+        .variable_data("temperature")
+        .unwrap()
+        .try_into()
+        .unwrap();
+    assert_eq!(temperature.len(), 6);
+    assert!((temperature[0] - 10.5).abs() < 0.0001);
+    assert!((temperature[5] - 15.75).abs() < 0.0001);
+}
+
+#[test]
+fn dataset_byte_ranges() {
+    let input = &fs::read("./data/swden/44097w9999.nc.dods").unwrap();
+    let dataset = DodsDataset::from_bytes(input).unwrap();
+
+    let ranges = dataset.variable_byte_ranges();
+    assert_eq!(ranges.len(), dataset.variables().len());
+    assert_eq!(ranges[0].0, "time");
+    assert_eq!(ranges[0].1, 0);
+
+    // Each variable starts exactly where the previous one ended, covering the whole binary section
+    ranges.windows(2).for_each(|w| assert_eq!(w[0].2, w[1].1));
+    assert_eq!(ranges[ranges.len() - 1].2, dataset.data_bytes.len());
+}
+
+#[test]
+fn dataset_repeated_access() {
+    let input = &fs::read("./data/swden/44097w9999.nc.dods").unwrap();
+    let dataset = DodsDataset::from_bytes(input).unwrap();
+
+    let layout = dataset.variable("mean_wave_dir").unwrap();
+    assert_eq!(layout.index, 3);
+    assert_eq!(layout.shape, vec![7, 64, 1, 1]);
+    assert_eq!(
+        Some(layout.offset),
+        dataset
+            .variable_byte_ranges()
+            .iter()
+            .find(|r| r.0 == "mean_wave_dir")
+            .map(|r| r.1)
+    );
+
+    let first: Vec<i32> = dataset
+        .variable_data("mean_wave_dir")
+        .unwrap()
+        .try_into()
+        .unwrap();
+    let second: Vec<i32> = dataset
+        .variable_data("mean_wave_dir")
+        .unwrap()
+        .try_into()
+        .unwrap();
+    assert_eq!(first, second);
+    assert!(dataset.variable("missing").is_none());
+}
+
+#[test]
+fn dataset_cdl() {
+    let dds = fs::read("./data/swden/44097w9999.nc.dods").unwrap();
+    let dds = String::from_utf8_lossy(&dds);
+    let dds = &dds[..dds.find("Data:").unwrap()];
+    let das = fs::read_to_string("./data/swden/44097w9999.nc.das")
+        .unwrap()
+        .replace(
+            "String units \"(meter * meter)/Hz\";",
+            "String units \"(meter * meter)/Hz\";\n        Int32 _ChunkSizes 1, 64, 1, 1;",
+        );
+
+    let dataset = Dataset::from_bytes(dds, &das).unwrap();
+    let cdl = dataset.to_cdl();
+
+    assert!(cdl.starts_with("netcdf 44097w9999 {\ndimensions:\n"));
+    assert!(cdl.contains(
+        "\ttime = 7 ;\n\tfrequency = 64 ;\n\tlatitude = 1 ;\n\tlongitude = 1 ;\nvariables:\n"
+    ));
+    assert!(cdl.contains("\tint time(time) ;\n"));
+    assert!(cdl.contains("\tfloat spectral_wave_density(time, frequency, latitude, longitude) ;\n"));
+    assert!(cdl.contains("\t\tspectral_wave_density:_FillValue = 999.0f ;\n"));
+    assert!(cdl.contains("\t\tspectral_wave_density:_ChunkSizes = 1, 64, 1, 1 ;\n"));
+    assert!(cdl.contains("\t\tmean_wave_dir:units = \"degrees_true\" ;\n"));
+    assert!(cdl.contains("// global attributes:\n\t\t:comment = \"Block Island, RI  (154)\" ;\n"));
+    assert!(cdl.ends_with("}\n"));
+}
+
+#[test]
+fn read_dataset_with_trailing_bytes() {
+    let mut input = fs::read("./data/swden/44097w9999.nc.dods").unwrap();
+    let (_, ignored) = DodsDataset::from_bytes_lenient(&input).unwrap();
+    assert_eq!(ignored, 0);
+
+    input.extend_from_slice(&[0xde; 16]);
+    let (dataset, ignored) = DodsDataset::from_bytes_lenient(&input).unwrap();
+    assert_eq!(ignored, 16);
+
+    let ranges = dataset.variable_byte_ranges();
+    assert_eq!(ranges[ranges.len() - 1].2, dataset.data_bytes.len());
+
+    let r2: Vec<f32> = dataset
+        .variable_data("wave_spectrum_r2")
+        .unwrap()
+        .try_into()
+        .unwrap();
+    assert_eq!(r2.len(), 7 * 64);
+}
+
+#[test]
+fn verify_dataset_against_dds() {
+    let input = &fs::read("./data/swden/44097w9999.nc.dods").unwrap();
+    let dataset = DodsDataset::from_bytes(input).unwrap();
+
+    let dds_text = String::from_utf8_lossy(input);
+    let dds_text = &dds_text[..dds_text.find("Data:").unwrap()];
+
+    let cached = DdsDataset::from_bytes(dds_text).unwrap();
+    assert!(dataset.verify_against(&cached).is_ok());
+
+    let stale = DdsDataset::from_bytes(&dds_text.replace("time = 7", "time = 6")).unwrap();
+    assert!(matches!(
+        dataset.verify_against(&stale),
+        Err(Error::SchemaMismatch)
+    ));
+}
+
+#[test]
+fn extract_embedded_dds() {
+    let input = &fs::read("./data/swden/44097w9999.nc.dods").unwrap();
+    let dds = fs::read_to_string("./data/swden/44097w9999.nc.dds").unwrap();
+
+    let dds_text = extract_dds_text(input).unwrap();
+    assert_eq!(dds_text, dds);
+    assert!(DdsDataset::from_bytes(dds_text).is_ok());
+
+    assert_eq!(extract_dds_text(b"Dataset {\n} empty;\n"), None);
+}
+
+#[test]
+fn read_dataset_with_variant_data_marker() {
+    let input = fs::read("./data/swden/44097w9999.nc.dods").unwrap();
+    let dds = fs::read_to_string("./data/swden/44097w9999.nc.dds").unwrap();
+    let binary = &input[dds.len() + "Data:\n".len()..];
+
+    for marker in ["DATA:\n", "Data :\n", "data:\n"] {
+        let mut bytes = dds.clone().into_bytes();
+        bytes.extend_from_slice(marker.as_bytes());
+        bytes.extend_from_slice(binary);
+
+        assert_eq!(extract_dds_text(&bytes), Some(dds.as_str()));
+
+        let dataset = DodsDataset::from_bytes(&bytes).unwrap();
+        assert_eq!(dataset.data_bytes, binary);
+        let time: Vec<i32> = dataset.variable_data("time").unwrap().try_into().unwrap();
+        assert_eq!(time.len(), 7);
+    }
+}
+
+#[test]
+fn read_dataset_with_latin1_name() {
+    let mut input = b"Dataset {\n    Int32 temp\xe9rature[x = 1];\n} latin1;\nData:\n".to_vec();
+    [1u32, 1, 21]
+        .iter()
+        .for_each(|v| input.extend_from_slice(&v.to_be_bytes()));
+
+    let dataset = DodsDataset::from_bytes(&input).unwrap();
+    assert_eq!(dataset.variables(), vec!["temp\u{fffd}rature"]);
+    let values: Vec<i32> = dataset
+        .variable_data("temp\u{fffd}rature")
+        .unwrap()
+        .try_into()
+        .unwrap();
+    assert_eq!(values, vec![21]);
+}
+
+#[test]
+fn read_dataset_with_string_array() {
+    let mut input =
+        b"Dataset {\n    Int32 x[x = 1];\n    String s[s = 1];\n    Int32 y[y = 1];\n} strings;\nData:\n"
+            .to_vec();
+    [1u32, 1, 7, 1, 1, 5]
+        .iter()
+        .for_each(|v| input.extend_from_slice(&v.to_be_bytes()));
+    input.extend_from_slice(b"abcd");
+
+    let dataset = DodsDataset::from_bytes(&input).unwrap();
+    let x: Vec<i32> = dataset.variable_data("x").unwrap().try_into().unwrap();
+    assert_eq!(x, vec![7]);
+
+    // Nothing past the string array can be located without decoding it
+    assert!(dataset.variable_data("s").is_err());
+    assert!(dataset.variable_data("y").is_err());
+    assert!(dataset.variable_checksum("y").is_err());
+    assert_eq!(dataset.variable_byte_ranges().len(), 1);
+    assert!(matches!(
+        DodsDataset::from_bytes_lenient(&input),
+        Err(Error::NotImplemented)
+    ));
+}
+
+#[test]
+fn dataset_variable_checksums() {
+    let mut input =
+        b"Dataset {\n    Int32 x[x = 2];\n    Float32 y[y = 1];\n} checksums;\nData:\n".to_vec();
+    [2u32, 2, 1, 2, 1, 1]
+        .iter()
+        .for_each(|v| input.extend_from_slice(&v.to_be_bytes()));
+    input.extend_from_slice(&0.5f32.to_be_bytes());
+
+    let dataset = DodsDataset::from_bytes(&input).unwrap();
+    assert_eq!(dataset.variable_checksum("x").unwrap(), 0x717f9e04);
+    assert_eq!(dataset.variable_checksum("y").unwrap(), 0xf986136b);
+    assert!(matches!(
+        dataset.variable_checksum("z"),
+        Err(Error::ParseError)
+    ));
+
+    let input = &fs::read("./data/swden/44097w9999.nc.dods").unwrap();
+    let dataset = DodsDataset::from_bytes(input).unwrap();
+    let checksum = dataset.variable_checksum("spectral_wave_density").unwrap();
+    assert_eq!(
+        dataset.variable_checksum("spectral_wave_density").unwrap(),
+        checksum
+    );
+    assert_ne!(dataset.variable_checksum("time").unwrap(), checksum);
+}
+
+#[test]
+fn encode_dataset_round_trip() {
+    let input = &fs::read("./data/swden/44097w9999.nc.dods").unwrap();
+    let dataset = DodsDataset::from_bytes(input).unwrap();
+
+    let mut arrays = HashMap::new();
+    for name in dataset.variables() {
+        arrays.insert(name.clone(), dataset.variable_data(&name).unwrap());
+        for (coord, data) in dataset.variable_coords(&name).unwrap() {
+            arrays.insert(coord, data);
+        }
+    }
+
+    let encoded = DodsEncoder::new(dataset.dds.clone(), arrays.clone())
+        .encode()
+        .unwrap();
+    let decoded = DodsDataset::from_bytes(&encoded).unwrap();
+    assert_eq!(decoded.dds.values, dataset.dds.values);
+    assert_eq!(decoded.data_bytes, dataset.data_bytes);
+
+    let r1: Vec<f32> = decoded
+        .variable_data("wave_spectrum_r1")
+        .unwrap()
+        .try_into()
+        .unwrap();
+    let expected: Vec<f32> = arrays["wave_spectrum_r1"].clone().try_into().unwrap();
+    assert_eq!(r1, expected);
+
+    arrays.insert("time".to_string(), DataArray::Int32(vec![0; 3]));
+    assert!(matches!(
+        DodsEncoder::new(dataset.dds.clone(), arrays.clone()).encode(),
+        Err(Error::CountMismatch)
+    ));
+
+    arrays.insert("time".to_string(), DataArray::Float32(vec![0.0; 7]));
+    assert!(matches!(
+        DodsEncoder::new(dataset.dds.clone(), arrays.clone()).encode(),
+        Err(Error::SchemaMismatch)
+    ));
+
+    arrays.remove("time");
+    assert!(matches!(
+        DodsEncoder::new(dataset.dds, arrays).encode(),
+        Err(Error::InvalidData)
+    ));
+}
+
+#[test]
+fn encode_padded_byte_array() {
+    let dds = DdsDatasetBuilder::new("flags.nc")
+        .add_array("qc", DataType::Byte, &[("time", 5)])
+        .add_array("time", DataType::Int32, &[("time", 5)])
+        .build()
+        .unwrap();
+
+    let mut arrays = HashMap::new();
+    arrays.insert("qc".to_string(), DataArray::Byte(vec![0, 1, 4, -1, -128]));
+    arrays.insert(
+        "time".to_string(),
+        DataArray::Int32(vec![10, 20, 30, 40, 50]),
+    );
+
+    let encoded = DodsEncoder::new(dds, arrays).encode().unwrap();
+    let dataset = DodsDataset::from_bytes(&encoded).unwrap();
+    assert_eq!(dataset.data_bytes.len(), 8 + 8 + 8 + 20);
+
+    let qc: Vec<u8> = dataset.variable_data("qc").unwrap().try_into().unwrap();
+    assert_eq!(qc, vec![0, 1, 4, 255, 128]);
+    assert_eq!(dataset.variable_data_iter("qc").unwrap().count(), 5);
+    assert!(matches!(
+        dataset.value_at("qc", &[3]).unwrap(),
+        DataValue::Byte(-1)
+    ));
+
+    let time: Vec<i32> = dataset.variable_data("time").unwrap().try_into().unwrap();
+    assert_eq!(time, vec![10, 20, 30, 40, 50]);
+}
+
+#[test]
+fn dataset_tidy_records() {
+    let dds = DdsDatasetBuilder::new("grid.nc")
+        .add_grid(
+            "sst",
+            DataType::Float32,
+            &[("lat", 2), ("lon", 2)],
+            &[DataType::Float32, DataType::Float32],
+        )
+        .build()
+        .unwrap();
+
+    let mut arrays = HashMap::new();
+    arrays.insert(
+        "sst".to_string(),
+        DataArray::Float32(vec![1.0, 2.0, 3.0, 4.0]),
+    );
+    arrays.insert("lat".to_string(), DataArray::Float32(vec![10.0, 20.0]));
+    arrays.insert("lon".to_string(), DataArray::Float32(vec![-70.0, -60.0]));
+
+    let encoded = DodsEncoder::new(dds, arrays).encode().unwrap();
+    let dataset = DodsDataset::from_bytes(&encoded).unwrap();
+
+    let records = dataset
+        .tidy_records("sst")
+        .unwrap()
+        .map(|r| {
+            let column = |name: &str| -> f32 { r[name].clone().try_into().unwrap() };
+            (column("lat"), column("lon"), column("sst"))
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        records,
+        vec![
+            (10.0, -70.0, 1.0),
+            (10.0, -60.0, 2.0),
+            (20.0, -70.0, 3.0),
+            (20.0, -60.0, 4.0),
+        ]
+    );
+
+    assert!(dataset.tidy_records("missing").is_err());
+
+    // Cutting into the maps or into the data array is an error rather than a panic
+    for cut in [10, 40] {
+        let truncated = DodsDataset::from_bytes(&encoded[..encoded.len() - cut]).unwrap();
+        assert!(matches!(
+            truncated.tidy_records("sst").map(|r| r.count()),
+            Err(Error::InvalidData)
+        ));
+        assert_eq!(truncated.variable_data("sst").is_err(), cut == 40);
+    }
+}
+
+#[test]
+fn dataset_value_at() {
+    let dds = DdsDatasetBuilder::new("field.nc")
+        .add_array("index", DataType::Int32, &[("y", 5)])
+        .add_array("field", DataType::Float32, &[("y", 5), ("x", 12)])
+        .build()
+        .unwrap();
+
+    let field = (0..5)
+        .flat_map(|y| (0..12).map(move |x| (y * 100 + x) as f32))
+        .collect();
+    let arrays = HashMap::from([
+        ("index".to_string(), DataArray::Int32(vec![0, 1, 2, 3, 4])),
+        ("field".to_string(), DataArray::Float32(field)),
+    ]);
+    let input = DodsEncoder::new(dds, arrays).encode().unwrap();
+    let dataset = DodsDataset::from_bytes(&input).unwrap();
+
+    assert!(matches!(
+        dataset.value_at("field", &[3, 10]).unwrap(),
+        DataValue::Float32(v) if v == 310.0
+    ));
+    assert!(matches!(
+        dataset.value_at("index", &[4]).unwrap(),
+        DataValue::Int32(4)
+    ));
+    assert!(matches!(
+        dataset.value_at("field", &[5, 0]),
+        Err(Error::InvalidData)
+    ));
+    assert!(matches!(
+        dataset.value_at("field", &[3]),
+        Err(Error::InvalidData)
+    ));
+
+    let input = &fs::read("./data/swden/44097w9999.nc.dods").unwrap();
+    let dataset = DodsDataset::from_bytes(input).unwrap();
+    let density: Vec<f32> = dataset
+        .variable_data("spectral_wave_density")
+        .unwrap()
+        .try_into()
+        .unwrap();
+    assert!(matches!(
+        dataset.value_at("spectral_wave_density", &[2, 17, 0, 0]).unwrap(),
+        DataValue::Float32(v) if v == density[2 * 64 + 17]
+    ));
+}
+
+#[test]
+fn dataset_raw_bytes() {
+    let input = &fs::read("./data/swden/44097w9999.nc.dods").unwrap();
+    let dataset = DodsDataset::from_bytes(input).unwrap();
+
+    let time = dataset.variable_raw_bytes("time").unwrap();
+    assert_eq!(time.len(), 7 * 4);
+
+    let density = dataset.variable_raw_bytes("spectral_wave_density").unwrap();
+    assert_eq!(density.len(), 7 * 64 * 4);
+
+    let values: Vec<f32> = dataset
+        .variable_data("spectral_wave_density")
+        .unwrap()
+        .try_into()
+        .unwrap();
+    assert_eq!(
+        f32::from_be_bytes(density[4..8].try_into().unwrap()),
+        values[1]
+    );
+
+    assert!(matches!(
+        dataset.variable_raw_bytes("missing"),
+        Err(Error::ParseError)
+    ));
+}
+
+#[test]
+fn dataset_count_headers() {
+    let mut input =
+        b"Dataset {\n    Int32 x[x = 2];\n    Int32 y[y = 1];\n} counts;\nData:\n".to_vec();
+    [2u32, 2, 1, 2, 1, 3, 9]
+        .iter()
+        .for_each(|v| input.extend_from_slice(&v.to_be_bytes()));
+
+    let dataset = DodsDataset::from_bytes(&input).unwrap();
+    assert_eq!(dataset.variable_count_headers("x").unwrap(), (2, 2));
+    assert_eq!(dataset.variable_count_headers("y").unwrap(), (1, 3));
+    assert!(matches!(
+        dataset.variable_data("y"),
+        Err(Error::CountMismatch)
+    ));
+
+    let input = &fs::read("./data/swden/44097w9999.nc.dods").unwrap();
+    let dataset = DodsDataset::from_bytes(input).unwrap();
+    assert_eq!(
+        dataset
+            .variable_count_headers("spectral_wave_density")
+            .unwrap(),
+        (7 * 64, 7 * 64)
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn dataset_to_geojson() {
+    let dds = DdsDatasetBuilder::new("stations.nc")
+        .add_array("lat", DataType::Float32, &[("station", 3)])
+        .add_array("lon", DataType::Float32, &[("station", 3)])
+        .add_array("wvht", DataType::Float32, &[("station", 3)])
+        .add_array("id", DataType::Int32, &[("id", 2)])
+        .build()
+        .unwrap();
+    let arrays = HashMap::from([
+        (
+            "lat".to_string(),
+            DataArray::Float32(vec![41.0, 40.5, 42.25]),
+        ),
+        (
+            "lon".to_string(),
+            DataArray::Float32(vec![-71.0, -69.5, -70.25]),
+        ),
+        (
+            "wvht".to_string(),
+            DataArray::Float32(vec![1.5, f32::NAN, 2.0]),
+        ),
+        ("id".to_string(), DataArray::Int32(vec![44097, 44008])),
+    ]);
+    let input = DodsEncoder::new(dds, arrays).encode().unwrap();
+    let dataset = DodsDataset::from_bytes(&input).unwrap();
+
+    let geojson = dataset.to_geojson("lat", "lon", "wvht").unwrap();
+    assert_eq!(geojson["type"], "FeatureCollection");
+
+    let features = geojson["features"].as_array().unwrap();
+    assert_eq!(features.len(), 3);
+    assert_eq!(features[0]["geometry"]["type"], "Point");
+    assert_eq!(
+        features[0]["geometry"]["coordinates"],
+        serde_json::json!([-71.0, 41.0])
+    );
+    assert_eq!(features[0]["properties"]["wvht"], 1.5);
+    assert!(features[1]["properties"]["wvht"].is_null());
+
+    assert!(matches!(
+        dataset.to_geojson("lat", "lon", "id"),
+        Err(Error::CountMismatch)
+    ));
+}